@@ -1,9 +1,8 @@
-use range_filters::U64_BITS;
-use range_filters::data_gen::generate_smooth_u16;
+use range_filters::data_gen::DataGen;
 use range_filters::diva::Diva;
 
 fn main() {
-    let mut keys = generate_smooth_u16(Some(3000));
+    let mut keys = DataGen::new(42).smooth_u16(Some(3000));
     keys.sort();
     let keys = keys.into_iter().map(|k| k as u64).collect::<Vec<_>>();
 
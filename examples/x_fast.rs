@@ -1,7 +1,7 @@
 use range_filters::x_fast_trie::XFastTrie;
 
 fn main() {
-    let mut trie = XFastTrie::new(8);
+    let mut trie: XFastTrie = XFastTrie::new(8);
 
     let keys = vec![10, 5, 15, 3, 12];
 
@@ -1,9 +1,9 @@
 use range_filters::U64_BITS;
-use range_filters::data_gen::generate_smooth_u64;
+use range_filters::data_gen::DataGen;
 use range_filters::y_fast_trie::YFastTrie;
 
 fn main() {
-    let mut keys = generate_smooth_u64(Some(1000));
+    let mut keys = DataGen::new(42).smooth_u64(Some(1000));
     keys.sort();
     println!("keys: {:?}", keys);
 
@@ -28,7 +28,7 @@ fn main() {
     );
     // println!("y-fast trie: {:?}", y_fast_trie);
 
-    let keys = (10..2000).into_iter().step_by(10).collect::<Vec<_>>();
+    let keys = (10..2000).into_iter().step_by(10).collect::<Vec<u64>>();
     println!("keys: {:?}", keys);
     let trie = YFastTrie::new_with_keys(&keys, 16);
 
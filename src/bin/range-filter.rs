@@ -0,0 +1,336 @@
+//! Build, save and query Diva filters over u64 keys from the command line
+//!
+//! Run with `--help` for the subcommands.
+
+use range_filters::Diva;
+use range_filters::datasets::{load_sosd_u64, load_text_u64};
+use range_filters::infix_store::MAX_ELEMENTS;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+
+const USAGE: &str = "usage: range-filter <command> [arguments]
+
+commands:
+  build KEYS OUTPUT   build a filter over the keys file and save it to OUTPUT
+    --format FORMAT     keys file format, text (one key per line) or sosd (default text)
+    --fpr F             false positive rate (default 0.01)
+    --target-size N     keys between samples, at most 2326 (default 1024)
+  query FILTER [QUERY]...
+                      answer each QUERY, a key or an inclusive range LO..HI
+    --file PATH         also answer the queries in PATH, one per line
+  stats FILTER        print the configuration, structure and memory of the filter
+  dump FILTER         print the whole filter
+  help                print this message
+
+query prints one line per query: the query, then `maybe` or `no`.";
+
+/// arguments of a command, split by `parse_options`
+struct CommandArgs {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+/// split `args` into positional arguments and the values of `options`
+fn parse_options(args: &[String], options: &[&str]) -> Result<CommandArgs, String> {
+    let mut parsed = CommandArgs {
+        positional: Vec::new(),
+        options: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            parsed.positional.push(arg.clone());
+            continue;
+        }
+        if !options.contains(&arg.as_str()) {
+            return Err(format!("unknown option {}", arg));
+        }
+        let value = args.next().ok_or(format!("missing value for {}", arg))?;
+        parsed.options.push((arg.clone(), value.clone()));
+    }
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid number {}", value))
+}
+
+/// a key, or an inclusive range written LO..HI
+fn parse_query(query: &str) -> Result<(u64, u64), String> {
+    match query.split_once("..") {
+        Some((lo, hi)) => {
+            let (lo, hi) = (parse_number(lo)?, parse_number(hi)?);
+            if lo > hi {
+                return Err(format!("empty range {}", query));
+            }
+            Ok((lo, hi))
+        }
+        None => {
+            let key = parse_number(query)?;
+            Ok((key, key))
+        }
+    }
+}
+
+fn load_filter(path: &str) -> Result<Diva, String> {
+    let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+    Diva::deserialize_from(&mut BufReader::new(file))
+        .map_err(|error| format!("{}: {}", path, error))
+}
+
+/// the single positional argument of a command taking a filter
+fn filter_path(positional: &[String]) -> Result<&str, String> {
+    match positional {
+        [path] => Ok(path),
+        _ => Err("expected one filter file".to_string()),
+    }
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let args = parse_options(args, &["--format", "--fpr", "--target-size"])?;
+    let [keys_path, output_path] = args.positional.as_slice() else {
+        return Err("expected a keys file and an output file".to_string());
+    };
+
+    let mut format = "text";
+    let mut fpr = 0.01;
+    let mut target_size = 1024;
+    for (option, value) in &args.options {
+        match option.as_str() {
+            "--format" => format = value,
+            "--fpr" => fpr = parse_number(value)?,
+            _ => target_size = parse_number(value)?,
+        }
+    }
+    if !(fpr > 0.0 && fpr < 1.0) {
+        return Err(format!("fpr {} is not in (0, 1)", fpr));
+    }
+    if !(1..=MAX_ELEMENTS).contains(&target_size) {
+        return Err(format!(
+            "target size {} is not in 1..={}",
+            target_size, MAX_ELEMENTS
+        ));
+    }
+
+    let keys = match format {
+        "text" => load_text_u64(keys_path),
+        "sosd" => load_sosd_u64(keys_path),
+        _ => return Err(format!("unknown format {}", format)),
+    }
+    .map_err(|error| format!("{}: {}", keys_path, error))?;
+    let diva = Diva::new_with_keys(&keys, target_size, fpr);
+
+    let file = File::create(output_path).map_err(|error| format!("{}: {}", output_path, error))?;
+    let mut writer = BufWriter::new(file);
+    diva.serialize_into(&mut writer)
+        .and_then(|()| writer.flush())
+        .map_err(|error| format!("{}: {}", output_path, error))?;
+
+    eprintln!(
+        "built a filter over {} keys, {} bits ({:.2} bits per key)",
+        keys.len(),
+        diva.size_in_bits(),
+        diva.size_in_bits() as f64 / keys.len().max(1) as f64
+    );
+    Ok(())
+}
+
+fn query(args: &[String], out: &mut impl Write) -> Result<(), String> {
+    let args = parse_options(args, &["--file"])?;
+    let Some((filter_path, queries)) = args.positional.split_first() else {
+        return Err("expected a filter file".to_string());
+    };
+
+    let mut queries = queries.to_vec();
+    for (_, path) in &args.options {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        queries.extend(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+
+    let diva = load_filter(filter_path)?;
+    for query in &queries {
+        let (lo, hi) = parse_query(query)?;
+        let answer = if lo == hi {
+            diva.may_contain(lo)
+        } else {
+            diva.may_contain_range(lo, hi)
+        };
+        writeln!(out, "{} {}", query, if answer { "maybe" } else { "no" })
+            .map_err(|error| error.to_string())?;
+    }
+    out.flush().map_err(|error| error.to_string())
+}
+
+fn stats(args: &[String], out: &mut impl Write) -> Result<(), String> {
+    let args = parse_options(args, &[])?;
+    let diva = load_filter(filter_path(&args.positional)?)?;
+    write!(out, "{}", diva.stats()).map_err(|error| error.to_string())
+}
+
+fn dump(args: &[String], out: &mut impl Write) -> Result<(), String> {
+    let args = parse_options(args, &[])?;
+    let diva = load_filter(filter_path(&args.positional)?)?;
+    write!(out, "{}", diva).map_err(|error| error.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    let result = match command.as_str() {
+        "build" => build(args),
+        "query" => query(args, &mut out),
+        "stats" => stats(args, &mut out),
+        "dump" => dump(args, &mut out),
+        "help" | "--help" => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {
+            eprintln!("unknown command {}\n\n{}", command, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = result.and_then(|()| out.flush().map_err(|error| error.to_string())) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// a path in the temp dir, unique to the test and the process
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("range_filter_{}_{}", std::process::id(), name))
+    }
+
+    /// build a filter over multiples of 1000, returns (keys path, filter path)
+    fn build_filter(name: &str) -> (String, String) {
+        let keys_path = temp_path(&format!("{}.txt", name));
+        let filter_path = temp_path(&format!("{}.diva", name));
+        let keys: Vec<String> = (1..=5000u64).map(|i| (i * 1000).to_string()).collect();
+        fs::write(&keys_path, keys.join("\n")).unwrap();
+
+        let keys_path = keys_path.to_str().unwrap().to_string();
+        let filter_path = filter_path.to_str().unwrap().to_string();
+        build(&args(&[&keys_path, &filter_path, "--fpr", "0.001"])).unwrap();
+        (keys_path, filter_path)
+    }
+
+    fn output(command: impl FnOnce(&mut Vec<u8>) -> Result<(), String>) -> String {
+        let mut out = Vec::new();
+        command(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_build_and_query() {
+        let (keys_path, filter_path) = build_filter("query");
+        let queries_path = temp_path("queries.txt");
+        fs::write(&queries_path, "# keys\n2000\n\n1500000..1500999\n").unwrap();
+        let queries_path = queries_path.to_str().unwrap();
+
+        let answers = output(|out| {
+            query(
+                &args(&[
+                    &filter_path,
+                    "1000",
+                    "5000000..9000000",
+                    "--file",
+                    queries_path,
+                ]),
+                out,
+            )
+        });
+        let lines: Vec<&str> = answers.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "1000 maybe",
+                "5000000..9000000 maybe",
+                "2000 maybe",
+                "1500000..1500999 maybe"
+            ]
+        );
+
+        // past the last key no filter answers maybe
+        let answers = output(|out| query(&args(&[&filter_path, "5000001..9000000"]), out));
+        assert_eq!(answers, "5000001..9000000 no\n");
+
+        let error = query(&args(&[&filter_path, "9..5"]), &mut Vec::new()).unwrap_err();
+        assert!(error.contains("empty range"));
+        let error = query(&args(&[&filter_path, "x"]), &mut Vec::new()).unwrap_err();
+        assert!(error.contains("invalid number"));
+        let error = query(&args(&[&keys_path]), &mut Vec::new()).unwrap_err();
+        assert!(error.contains("not a serialized Diva"));
+
+        for path in [&keys_path, &filter_path, queries_path] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let (keys_path, filter_path) = build_filter("stats");
+        let summary = output(|out| stats(&args(&[&filter_path]), out));
+        assert!(summary.contains("Keys:                 5000"));
+        assert!(summary.contains("Remainder size:       11 bits"));
+        assert!(summary.contains("Bits per key:"));
+
+        let error = stats(&args(&[&filter_path, &filter_path]), &mut Vec::new()).unwrap_err();
+        assert!(error.contains("expected one filter file"));
+        let error = stats(&args(&["/nonexistent/filter"]), &mut Vec::new()).unwrap_err();
+        assert!(error.starts_with("/nonexistent/filter"));
+
+        fs::remove_file(keys_path).unwrap();
+        fs::remove_file(filter_path).unwrap();
+    }
+
+    #[test]
+    fn test_build_invalid() {
+        let (keys_path, filter_path) = build_filter("invalid");
+        let build_with = |options: &[&str]| {
+            let mut build_args = vec![keys_path.as_str(), filter_path.as_str()];
+            build_args.extend(options);
+            build(&args(&build_args)).unwrap_err()
+        };
+
+        assert_eq!(
+            build_with(&["--target-size", "4096"]),
+            format!("target size 4096 is not in 1..={}", MAX_ELEMENTS)
+        );
+        assert!(build_with(&["--target-size", "0"]).contains("is not in"));
+        assert!(build_with(&["--fpr", "1.5"]).contains("is not in (0, 1)"));
+        assert!(build_with(&["--format", "csv"]).contains("unknown format"));
+        assert!(build_with(&["--format", "sosd"]).starts_with(keys_path.as_str()));
+        assert!(build_with(&["--bits", "8"]).contains("unknown option"));
+        assert!(build(&args(&[&keys_path])).is_err());
+
+        // the largest target size builds
+        build(&args(&[&keys_path, &filter_path, "--target-size", "2326"])).unwrap();
+
+        fs::remove_file(keys_path).unwrap();
+        fs::remove_file(filter_path).unwrap();
+    }
+}
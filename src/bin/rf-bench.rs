@@ -0,0 +1,257 @@
+//! Diva benchmark: construction time, point/range query throughput, FPR and bits per key
+//!
+//! Prints one CSV row per range length, run with `--help` for the arguments.
+
+use range_filters::data_gen::{DataGen, RangeLenDistribution};
+use range_filters::datasets::{load_sosd_u64, load_text_u64};
+use range_filters::infix_store::MAX_ELEMENTS;
+use range_filters::{Diva, evaluate};
+use std::hint::black_box;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: rf-bench [options]
+
+keys, generated unless a file is given:
+  --keys N            number of generated keys (default 1000000)
+  --dist NAME         uniform, normal, zipfian, clustered, sequential or shared-prefix
+                      (default uniform)
+  --sosd PATH         load u64 keys from a SOSD binary file
+  --text PATH         load u64 keys from a text file, one key per line
+  --seed N            seed of generated keys and queries (default 42)
+
+filter and queries:
+  --target-size N     keys between samples, at most 2326 (default 1024)
+  --fpr F             false positive rate (default 0.01)
+  --queries N         queries per range length (default 100000)
+  --range-lens LIST   comma separated range lengths, 1 runs point queries (default 1,16,1024)
+  --no-header         skip the CSV header
+  --help              print this message";
+
+const CSV_HEADER: &str = "dataset,keys,target_size,fpr,build_ms,bits_per_key,query,range_len,\
+queries,ns_per_query,mqps,empty_queries,false_positives,measured_fpr,estimated_fpr";
+
+struct Args {
+    num_keys: usize,
+    dist: String,
+    sosd: Option<String>,
+    text: Option<String>,
+    seed: u64,
+    target_size: usize,
+    fpr: f64,
+    queries: usize,
+    range_lens: Vec<u64>,
+    header: bool,
+}
+
+impl Args {
+    /// parse the command line, `None` if `--help` was given
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut parsed = Self {
+            num_keys: 1_000_000,
+            dist: "uniform".to_string(),
+            sosd: None,
+            text: None,
+            seed: 42,
+            target_size: 1024,
+            fpr: 0.01,
+            queries: 100_000,
+            range_lens: vec![1, 16, 1024],
+            header: true,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--keys" => parsed.num_keys = parse_number(&value()?)?,
+                "--dist" => parsed.dist = value()?,
+                "--sosd" => parsed.sosd = Some(value()?),
+                "--text" => parsed.text = Some(value()?),
+                "--seed" => parsed.seed = parse_number(&value()?)?,
+                "--target-size" => parsed.target_size = parse_number(&value()?)?,
+                "--fpr" => parsed.fpr = parse_number(&value()?)?,
+                "--queries" => parsed.queries = parse_number(&value()?)?,
+                "--range-lens" => {
+                    parsed.range_lens = value()?
+                        .split(',')
+                        .map(parse_number)
+                        .collect::<Result<_, _>>()?
+                }
+                "--no-header" => parsed.header = false,
+                "--help" => return Ok(None),
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+
+        if !(1..=MAX_ELEMENTS).contains(&parsed.target_size) {
+            return Err(format!(
+                "target size {} is not in 1..={}",
+                parsed.target_size, MAX_ELEMENTS
+            ));
+        }
+        if parsed.range_lens.contains(&0) {
+            return Err("range lengths must be positive".to_string());
+        }
+        if !(parsed.fpr > 0.0 && parsed.fpr < 1.0) {
+            return Err(format!("fpr {} is not in (0, 1)", parsed.fpr));
+        }
+        Ok(Some(parsed))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number {}", value))
+}
+
+/// quote a CSV field if it holds a comma, quote or line break, doubling inner quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// keys to build the filter from and the name of the dataset
+fn load_keys(args: &Args, data_gen: &mut DataGen) -> Result<(Vec<u64>, String), String> {
+    if let Some(path) = &args.sosd {
+        let keys = load_sosd_u64(path).map_err(|error| format!("{}: {}", path, error))?;
+        return Ok((keys, path.clone()));
+    }
+    if let Some(path) = &args.text {
+        let keys = load_text_u64(path).map_err(|error| format!("{}: {}", path, error))?;
+        return Ok((keys, path.clone()));
+    }
+
+    let count = args.num_keys;
+    let keys = match args.dist.as_str() {
+        "uniform" => data_gen.uniform_u64(count, 0, u64::MAX),
+        "normal" => data_gen.smooth_u64(Some(count)),
+        "zipfian" => data_gen.zipfian_u64(count, 0, u64::MAX, 1.1),
+        "clustered" => data_gen.clustered_u64(count, 0, u64::MAX, 1000, 1 << 32),
+        "sequential" => data_gen.sequential_u64(count, 0, 0.01, 1 << 20),
+        "shared-prefix" => data_gen.shared_prefix_u64(count, 64, 24),
+        dist => return Err(format!("unknown distribution {}", dist)),
+    };
+    Ok((keys, args.dist.clone()))
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut data_gen = DataGen::new(args.seed);
+    let (keys, dataset) = load_keys(args, &mut data_gen)?;
+    let (Some(&min_key), Some(&max_key)) = (keys.iter().min(), keys.iter().max()) else {
+        return Err("no keys".to_string());
+    };
+
+    let start = Instant::now();
+    let diva = Diva::new_with_keys(&keys, args.target_size, args.fpr);
+    let build_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    // duplicate keys are stored once, count them once like `evaluate` does
+    let mut distinct_keys = keys.clone();
+    distinct_keys.sort_unstable();
+    distinct_keys.dedup();
+    let num_keys = distinct_keys.len();
+    let bits_per_key = diva.size_in_bits() as f64 / num_keys as f64;
+
+    if args.header {
+        println!("{}", CSV_HEADER);
+    }
+    for &range_len in &args.range_lens {
+        let queries = data_gen.uniform_queries(
+            args.queries,
+            min_key,
+            max_key,
+            RangeLenDistribution::Fixed(range_len),
+        );
+
+        let start = Instant::now();
+        let positives = if range_len == 1 {
+            queries
+                .iter()
+                .filter(|&&(lo, _)| diva.may_contain(black_box(lo)))
+                .count()
+        } else {
+            queries
+                .iter()
+                .filter(|&&(lo, hi)| diva.may_contain_range(black_box(lo), black_box(hi)))
+                .count()
+        };
+        let elapsed = start.elapsed().as_secs_f64();
+        black_box(positives);
+
+        let evaluation = evaluate(&diva, &keys, queries.iter().copied());
+        println!(
+            "{},{},{},{},{:.3},{:.3},{},{},{},{:.1},{:.3},{},{},{:.6},{:.6}",
+            csv_field(&dataset),
+            num_keys,
+            args.target_size,
+            args.fpr,
+            build_ms,
+            bits_per_key,
+            if range_len == 1 { "point" } else { "range" },
+            range_len,
+            queries.len(),
+            elapsed * 1e9 / queries.len().max(1) as f64,
+            queries.len() as f64 / elapsed / 1e6,
+            evaluation.empty_queries(),
+            evaluation.false_positives(),
+            evaluation.fpr(),
+            diva.estimated_fpr(&[(range_len, 1.0)]),
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["--keys", "10", "--range-lens", "1,8"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.num_keys, 10);
+        assert_eq!(args.range_lens, vec![1, 8]);
+
+        assert!(parse(&["--keys", "10", "--help"]).unwrap().is_none());
+        assert!(parse(&["--target-size", "4096"]).is_err_and(|error| error.contains("is not in")));
+        assert!(parse(&["--range-lens", "0"]).is_err());
+        assert!(parse(&["--keys"]).is_err_and(|error| error.contains("missing value")));
+        assert!(parse(&["--bits"]).is_err_and(|error| error.contains("unknown argument")));
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("uniform"), "uniform");
+        assert_eq!(csv_field("/data/keys,v2"), "\"/data/keys,v2\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+    }
+}
@@ -1,6 +1,9 @@
 use crate::Key;
+use crate::UnsignedKey;
 use crate::infix_store::InfixStore;
+use crate::utils::arc_allocation_size;
 use std::fmt;
+use std::mem::size_of;
 use std::sync::{Arc, RwLock};
 
 // #[derive(Debug, Default)]
@@ -8,24 +11,24 @@ use std::sync::{Arc, RwLock};
 
 // TODO: add cached count
 #[derive(Debug, Default)]
-pub struct BinarySearchTreeGroup {
-    pub root: Option<Box<TreeNode>>,
+pub struct BinarySearchTreeGroup<K: UnsignedKey = Key> {
+    pub root: Option<Box<TreeNode<K>>>,
 }
 
 #[derive(Clone, Debug)]
-pub struct TreeNode {
-    pub key: Key,
-    pub left: Option<Box<TreeNode>>,
-    pub right: Option<Box<TreeNode>>,
+pub struct TreeNode<K: UnsignedKey = Key> {
+    pub key: K,
+    pub left: Option<Box<TreeNode<K>>>,
+    pub right: Option<Box<TreeNode<K>>>,
     pub infix_store: Option<Arc<RwLock<InfixStore>>>,
 }
 
-impl BinarySearchTreeGroup {
+impl<K: UnsignedKey> BinarySearchTreeGroup<K> {
     pub fn new() -> Self {
         Self { root: None }
     }
 
-    pub fn new_with_keys(keys: &[Key]) -> Self {
+    pub fn new_with_keys(keys: &[K]) -> Self {
         if keys.is_empty() {
             return Self { root: None };
         }
@@ -37,7 +40,7 @@ impl BinarySearchTreeGroup {
         Self { root }
     }
 
-    fn top_down_bst_insertion(keys: &[Key], start: isize, end: isize) -> Option<Box<TreeNode>> {
+    fn top_down_bst_insertion(keys: &[K], start: isize, end: isize) -> Option<Box<TreeNode<K>>> {
         if start > end {
             return None;
         }
@@ -52,23 +55,126 @@ impl BinarySearchTreeGroup {
         Some(root)
     }
 
+    /// split the group in two: keys >= `key` move to the returned group along with
+    /// their infix stores, both groups are rebuilt balanced
+    pub fn split_off(&mut self, key: K) -> Self {
+        let mut nodes = Vec::new();
+        Self::take_nodes_in_order(self.root.take(), &mut nodes);
+
+        let split_index = nodes.partition_point(|(k, _)| *k < key);
+        let upper_nodes = nodes.split_off(split_index);
+
+        self.root = Self::balanced_from_nodes(&mut nodes.into_iter(), split_index);
+        let upper_len = upper_nodes.len();
+        Self {
+            root: Self::balanced_from_nodes(&mut upper_nodes.into_iter(), upper_len),
+        }
+    }
+
+    /// remove a key and its infix store, the group is rebuilt balanced
+    /// returns false if the key is not in the group
+    pub fn remove(&mut self, key: K) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+
+        let mut nodes = Vec::new();
+        Self::take_nodes_in_order(self.root.take(), &mut nodes);
+        nodes.retain(|(k, _)| *k != key);
+
+        let count = nodes.len();
+        self.root = Self::balanced_from_nodes(&mut nodes.into_iter(), count);
+        true
+    }
+
+    /// drain the tree into (key, infix_store) pairs in sorted order
+    fn take_nodes_in_order(
+        node: Option<Box<TreeNode<K>>>,
+        nodes: &mut Vec<(K, Option<Arc<RwLock<InfixStore>>>)>,
+    ) {
+        if let Some(n) = node {
+            let n = *n;
+            Self::take_nodes_in_order(n.left, nodes);
+            nodes.push((n.key, n.infix_store));
+            Self::take_nodes_in_order(n.right, nodes);
+        }
+    }
+
+    /// build a balanced tree from the next `count` sorted (key, infix_store) pairs
+    fn balanced_from_nodes(
+        nodes: &mut impl Iterator<Item = (K, Option<Arc<RwLock<InfixStore>>>)>,
+        count: usize,
+    ) -> Option<Box<TreeNode<K>>> {
+        if count == 0 {
+            return None;
+        }
+
+        // in-order build: left subtree, then this node, then right subtree
+        let left_count = count / 2;
+        let left = Self::balanced_from_nodes(nodes, left_count);
+        let (key, infix_store) = nodes.next()?;
+        let right = Self::balanced_from_nodes(nodes, count - left_count - 1);
+
+        Some(Box::new(TreeNode {
+            key,
+            left,
+            right,
+            infix_store,
+        }))
+    }
+
+    /// bytes used by the group and its tree nodes
+    /// infix stores are counted by `infix_stores_size_in_bytes`, except for the shared
+    /// pointer and lock wrapping each of them
+    pub fn size_in_bytes(&self) -> usize {
+        let mut bytes = size_of::<Self>();
+        Self::visit_nodes(&self.root, &mut |node| {
+            bytes += size_of::<TreeNode<K>>();
+            if node.infix_store.is_some() {
+                bytes += arc_allocation_size::<RwLock<InfixStore>>() - size_of::<InfixStore>();
+            }
+        });
+        bytes
+    }
+
+    /// bytes used by the infix stores attached to the tree nodes
+    pub fn infix_stores_size_in_bytes(&self) -> usize {
+        let mut bytes = 0;
+        Self::visit_nodes(&self.root, &mut |node| {
+            if let Some(infix_store) = &node.infix_store
+                && let Ok(infix_store) = infix_store.read()
+            {
+                bytes += infix_store.size_in_bytes();
+            }
+        });
+        bytes
+    }
+
+    fn visit_nodes(node: &Option<Box<TreeNode<K>>>, visit: &mut impl FnMut(&TreeNode<K>)) {
+        if let Some(n) = node {
+            Self::visit_nodes(&n.left, visit);
+            visit(n);
+            Self::visit_nodes(&n.right, visit);
+        }
+    }
+
     // TODO: use cached length
     pub fn len(&self) -> usize {
         Self::len_recursive(&self.root)
     }
 
-    fn len_recursive(node: &Option<Box<TreeNode>>) -> usize {
+    fn len_recursive(node: &Option<Box<TreeNode<K>>>) -> usize {
         match node {
             None => 0,
             Some(n) => 1 + Self::len_recursive(&n.left) + Self::len_recursive(&n.right),
         }
     }
 
-    pub fn insert(&mut self, key: Key) {
+    pub fn insert(&mut self, key: K) {
         Self::insert_recursive(&mut self.root, key);
     }
 
-    fn insert_recursive(node: &mut Option<Box<TreeNode>>, key: Key) {
+    fn insert_recursive(node: &mut Option<Box<TreeNode<K>>>, key: K) {
         match node {
             None => {
                 *node = Some(Box::new(TreeNode {
@@ -88,11 +194,11 @@ impl BinarySearchTreeGroup {
         }
     }
 
-    pub fn contains(&self, key: Key) -> bool {
+    pub fn contains(&self, key: K) -> bool {
         Self::contains_recursive(&self.root, key)
     }
 
-    fn contains_recursive(node: &Option<Box<TreeNode>>, key: Key) -> bool {
+    fn contains_recursive(node: &Option<Box<TreeNode<K>>>, key: K) -> bool {
         match node {
             None => false,
             Some(n) => {
@@ -107,7 +213,7 @@ impl BinarySearchTreeGroup {
         }
     }
 
-    fn find_node_mut(node: &mut Option<Box<TreeNode>>, key: Key) -> Option<&mut TreeNode> {
+    fn find_node_mut(node: &mut Option<Box<TreeNode<K>>>, key: K) -> Option<&mut TreeNode<K>> {
         match node {
             None => None,
             Some(n) => {
@@ -122,19 +228,19 @@ impl BinarySearchTreeGroup {
         }
     }
 
-    pub fn set_infix_store(&mut self, key: Key, infix_store: InfixStore) {
+    pub fn set_infix_store(&mut self, key: K, infix_store: InfixStore) {
         if let Some(node) = Self::find_node_mut(&mut self.root, key) {
             node.infix_store = Some(Arc::new(RwLock::new(infix_store)));
         }
     }
 
-    pub fn get_infix_store(&self, key: Key) -> Option<Arc<RwLock<InfixStore>>> {
+    pub fn get_infix_store(&self, key: K) -> Option<Arc<RwLock<InfixStore>>> {
         Self::get_infix_store_recursive(&self.root, key)
     }
 
     fn get_infix_store_recursive(
-        node: &Option<Box<TreeNode>>,
-        key: Key,
+        node: &Option<Box<TreeNode<K>>>,
+        key: K,
     ) -> Option<Arc<RwLock<InfixStore>>> {
         match node {
             None => None,
@@ -150,19 +256,19 @@ impl BinarySearchTreeGroup {
         }
     }
 
-    pub fn predecessor_infix_store(&self, key: Key) -> Option<Arc<RwLock<InfixStore>>> {
+    pub fn predecessor_infix_store(&self, key: K) -> Option<Arc<RwLock<InfixStore>>> {
         Self::predecessor_store_recursive(&self.root, key, None)
     }
 
-    pub fn predecessor(&self, key: Key) -> Option<Key> {
+    pub fn predecessor(&self, key: K) -> Option<K> {
         Self::predecessor_recursive(&self.root, key, None)
     }
 
     fn predecessor_recursive(
-        node: &Option<Box<TreeNode>>,
-        key: Key,
-        best: Option<Key>,
-    ) -> Option<Key> {
+        node: &Option<Box<TreeNode<K>>>,
+        key: K,
+        best: Option<K>,
+    ) -> Option<K> {
         match node {
             None => best,
             Some(n) => {
@@ -177,15 +283,11 @@ impl BinarySearchTreeGroup {
         }
     }
 
-    pub fn successor(&self, key: Key) -> Option<Key> {
+    pub fn successor(&self, key: K) -> Option<K> {
         Self::successor_recursive(&self.root, key, None)
     }
 
-    fn successor_recursive(
-        node: &Option<Box<TreeNode>>,
-        key: Key,
-        best: Option<Key>,
-    ) -> Option<Key> {
+    fn successor_recursive(node: &Option<Box<TreeNode<K>>>, key: K, best: Option<K>) -> Option<K> {
         match node {
             None => best,
             Some(n) => {
@@ -201,8 +303,8 @@ impl BinarySearchTreeGroup {
     }
 
     fn predecessor_store_recursive(
-        node: &Option<Box<TreeNode>>,
-        key: Key,
+        node: &Option<Box<TreeNode<K>>>,
+        key: K,
         best: Option<Arc<RwLock<InfixStore>>>,
     ) -> Option<Arc<RwLock<InfixStore>>> {
         match node {
@@ -219,13 +321,13 @@ impl BinarySearchTreeGroup {
         }
     }
 
-    pub fn successor_infix_store(&self, key: Key) -> Option<Arc<RwLock<InfixStore>>> {
+    pub fn successor_infix_store(&self, key: K) -> Option<Arc<RwLock<InfixStore>>> {
         Self::successor_store_recursive(&self.root, key, None)
     }
 
     fn successor_store_recursive(
-        node: &Option<Box<TreeNode>>,
-        key: Key,
+        node: &Option<Box<TreeNode<K>>>,
+        key: K,
         best: Option<Arc<RwLock<InfixStore>>>,
     ) -> Option<Arc<RwLock<InfixStore>>> {
         match node {
@@ -243,7 +345,7 @@ impl BinarySearchTreeGroup {
     }
 
     #[allow(dead_code)]
-    fn min_key(node: &Option<Box<TreeNode>>) -> Option<Key> {
+    fn min_key(node: &Option<Box<TreeNode<K>>>) -> Option<K> {
         match node {
             None => None,
             Some(n) => {
@@ -257,7 +359,7 @@ impl BinarySearchTreeGroup {
     }
 
     #[allow(dead_code)]
-    fn max_key(node: &Option<Box<TreeNode>>) -> Option<Key> {
+    fn max_key(node: &Option<Box<TreeNode<K>>>) -> Option<K> {
         match node {
             None => None,
             Some(n) => {
@@ -271,7 +373,7 @@ impl BinarySearchTreeGroup {
     }
 
     #[allow(dead_code)]
-    fn min_node(node: &Option<Box<TreeNode>>) -> Option<&TreeNode> {
+    fn min_node(node: &Option<Box<TreeNode<K>>>) -> Option<&TreeNode<K>> {
         match node {
             None => None,
             Some(n) => {
@@ -285,7 +387,7 @@ impl BinarySearchTreeGroup {
     }
 
     #[allow(dead_code)]
-    fn max_node(node: &Option<Box<TreeNode>>) -> Option<&TreeNode> {
+    fn max_node(node: &Option<Box<TreeNode<K>>>) -> Option<&TreeNode<K>> {
         match node {
             None => None,
             Some(n) => {
@@ -303,7 +405,7 @@ impl BinarySearchTreeGroup {
     }
 
     fn format_tree(
-        node: &Option<Box<TreeNode>>,
+        node: &Option<Box<TreeNode<K>>>,
         prefix: &str,
         is_tail: bool,
         f: &mut fmt::Formatter,
@@ -332,7 +434,7 @@ impl BinarySearchTreeGroup {
     }
 }
 
-impl fmt::Display for BinarySearchTreeGroup {
+impl<K: UnsignedKey> fmt::Display for BinarySearchTreeGroup<K> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "\n=== Binary Search Tree ===")?;
         if self.root.is_none() {
@@ -351,7 +453,8 @@ mod tests {
 
     #[test]
     fn test_tree_construction() {
-        let bst = BinarySearchTreeGroup::new_with_keys(&[1, 2, 3, 20, 30, 4, 5, 6, 7]);
+        let bst: BinarySearchTreeGroup =
+            BinarySearchTreeGroup::new_with_keys(&[1, 2, 3, 20, 30, 4, 5, 6, 7]);
         assert!(bst.contains(1));
         assert!(bst.contains(2));
         assert!(bst.contains(30));
@@ -366,7 +469,7 @@ mod tests {
 
     #[test]
     fn test_tree_insertion() {
-        let mut bst = BinarySearchTreeGroup::new();
+        let mut bst: BinarySearchTreeGroup = BinarySearchTreeGroup::new();
         bst.insert(1);
         bst.insert(2);
         bst.insert(3);
@@ -392,7 +495,8 @@ mod tests {
 
     #[test]
     fn test_predecessor_infix_store() {
-        let mut bst = BinarySearchTreeGroup::new_with_keys(&[10, 20, 30, 40, 50]);
+        let mut bst: BinarySearchTreeGroup =
+            BinarySearchTreeGroup::new_with_keys(&[10, 20, 30, 40, 50]);
 
         bst.set_infix_store(10, InfixStore::default());
         bst.set_infix_store(20, InfixStore::default());
@@ -421,4 +525,54 @@ mod tests {
         let pred_60 = bst.predecessor_infix_store(60).unwrap();
         assert!(Arc::ptr_eq(&store_50, &pred_60));
     }
+
+    #[test]
+    fn test_split_off() {
+        let mut bst: BinarySearchTreeGroup =
+            BinarySearchTreeGroup::new_with_keys(&[10, 20, 30, 40, 50, 60]);
+        bst.set_infix_store(20, InfixStore::default());
+        bst.set_infix_store(40, InfixStore::default());
+        let store_40 = bst.get_infix_store(40).unwrap();
+
+        let upper = bst.split_off(35);
+
+        assert_eq!(bst.len(), 3);
+        assert_eq!(upper.len(), 3);
+        for key in [10, 20, 30] {
+            assert!(bst.contains(key));
+            assert!(!upper.contains(key));
+        }
+        for key in [40, 50, 60] {
+            assert!(upper.contains(key));
+            assert!(!bst.contains(key));
+        }
+
+        // infix stores move with their keys
+        assert!(bst.get_infix_store(20).is_some());
+        assert!(Arc::ptr_eq(&upper.get_infix_store(40).unwrap(), &store_40));
+        assert!(upper.get_infix_store(50).is_none());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut bst: BinarySearchTreeGroup =
+            BinarySearchTreeGroup::new_with_keys(&[10, 20, 30, 40, 50]);
+        bst.set_infix_store(40, InfixStore::default());
+        let store_40 = bst.get_infix_store(40).unwrap();
+
+        assert!(bst.remove(30));
+        assert!(!bst.remove(30));
+        assert!(!bst.remove(35));
+
+        assert_eq!(bst.len(), 4);
+        assert!(!bst.contains(30));
+        assert_eq!(bst.predecessor(35), Some(20));
+        assert_eq!(bst.successor(35), Some(40));
+        assert!(Arc::ptr_eq(&bst.get_infix_store(40).unwrap(), &store_40));
+
+        for key in [10, 20, 40, 50] {
+            assert!(bst.remove(key));
+        }
+        assert!(bst.root.is_none());
+    }
 }
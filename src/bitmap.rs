@@ -72,6 +72,7 @@ fn select_in_word(word: u64, rank: usize) -> Option<usize> {
 
 /// optimized rank using cached halfway popcount
 /// if pos is in second half, start counting from cached midpoint
+/// `cached_popcount` is `rank(data, half_pos)`, half_pos need not be word aligned
 #[inline]
 pub fn rank_cached(data: &[u64], pos: usize, half_pos: usize, cached_popcount: usize) -> usize {
     if pos <= half_pos {
@@ -79,14 +80,16 @@ pub fn rank_cached(data: &[u64], pos: usize, half_pos: usize, cached_popcount: u
         rank(data, pos)
     } else {
         // query is in second half, start from cached count
+        // counting from the word holding half_pos, minus its bits below half_pos
         let word_offset = half_pos / U64_BIT_SIZE;
-        let remaining = rank(&data[word_offset..], pos - half_pos);
-        cached_popcount + remaining
+        let remaining = rank(&data[word_offset..], pos - word_offset * U64_BIT_SIZE);
+        cached_popcount + remaining - rank(&data[word_offset..], half_pos % U64_BIT_SIZE)
     }
 }
 
 /// optimized select using cached halfway popcount
 /// if target rank is past cached count, start from midpoint
+/// `cached_popcount` is `rank(data, half_pos)`, half_pos need not be word aligned
 #[inline]
 pub fn select_cached(
     data: &[u64],
@@ -99,11 +102,13 @@ pub fn select_cached(
         select(data, rank_val)
     } else {
         // target is in second half, start from cached midpoint
-        let remaining_rank = rank_val - cached_popcount;
+        // searching from the word holding half_pos, counting its bits below half_pos
         let word_offset = half_pos / U64_BIT_SIZE;
+        let remaining_rank =
+            rank_val - cached_popcount + rank(&data[word_offset..], half_pos % U64_BIT_SIZE);
 
         // search in second half
-        select(&data[word_offset..], remaining_rank).map(|pos| pos + half_pos)
+        select(&data[word_offset..], remaining_rank).map(|pos| pos + word_offset * U64_BIT_SIZE)
     }
 }
 
@@ -201,4 +206,30 @@ mod tests {
             assert_eq!(rank(&data, expected_pos + 1), rank_ + 1usize);
         }
     }
+
+    #[test]
+    fn test_cached_rank_select() {
+        let mut data = vec![0u64; 4];
+        let positions = vec![1, 7, 15, 63, 64, 100, 115, 116, 200, 255];
+        for &pos in &positions {
+            set_bit(&mut data, pos);
+        }
+
+        // the cached helpers agree with the plain ones, aligned halfway point or not
+        for half_pos in [0, 64, 115, 116, 128, 231] {
+            let cached_popcount = rank(&data, half_pos);
+            for pos in 0..=256 {
+                assert_eq!(
+                    rank_cached(&data, pos, half_pos, cached_popcount),
+                    rank(&data, pos)
+                );
+            }
+            for rank_ in 0..=positions.len() {
+                assert_eq!(
+                    select_cached(&data, rank_, half_pos, cached_popcount),
+                    select(&data, rank_)
+                );
+            }
+        }
+    }
 }
@@ -1,139 +1,477 @@
-use rand::Rng;
-use rand::thread_rng;
-use rand_distr::{Distribution, Normal, Uniform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal, Uniform, Zipf};
 
 // default = 64k keys
 const DEFAULT_COUNT: usize = 1 << 16;
 
-pub fn generate_normal_u64(count: usize, mean: f64, std_dev: f64) -> Vec<u64> {
-    let normal = Normal::new(mean, std_dev).unwrap();
-    let mut rng = thread_rng();
+/// Random keys and queries, reproducible from a seed
+///
+/// The same seed gives the same data on every run, so benchmarks and failing tests can
+/// be replayed. The free `generate_*` functions draw from a fresh entropy seed.
+///
+/// # Example
+/// ```rust
+/// use range_filters::data_gen::DataGen;
+/// let keys = DataGen::new(42).uniform_u64(100, 0, 1000);
+/// assert_eq!(keys, DataGen::new(42).uniform_u64(100, 0, 1000));
+/// ```
+pub struct DataGen {
+    rng: StdRng,
+}
 
-    (0..count)
-        .map(|_| {
-            let sample: f64 = normal.sample(&mut rng);
-            sample.max(0.0).min(u64::MAX as f64) as u64
-        })
-        .collect()
+/// Length distribution of generated range queries, a length of 1 is a point query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeLenDistribution {
+    Fixed(u64),
+    /// lengths uniform in [min, max]
+    Uniform {
+        min: u64,
+        max: u64,
+    },
+    /// lengths 2^e with e uniform in [min_log2, max_log2], spread over orders of magnitude
+    LogUniform {
+        min_log2: u32,
+        max_log2: u32,
+    },
 }
 
-pub fn generate_normal_u32(count: usize, mean: f64, std_dev: f64) -> Vec<u32> {
-    let normal = Normal::new(mean, std_dev).unwrap();
-    let mut rng = thread_rng();
+impl RangeLenDistribution {
+    /// draw a length, at least 1
+    pub fn sample(&self, rng: &mut impl Rng) -> u64 {
+        let len = match *self {
+            Self::Fixed(len) => len,
+            Self::Uniform { min, max } => rng.gen_range(min..=max),
+            Self::LogUniform { min_log2, max_log2 } => 1u64
+                .checked_shl(rng.gen_range(min_log2..=max_log2))
+                .unwrap_or(u64::MAX),
+        };
+        len.max(1)
+    }
+}
 
-    (0..count)
-        .map(|_| {
-            let sample: f64 = normal.sample(&mut rng);
-            sample.max(0.0).min(u32::MAX as f64) as u32
-        })
-        .collect()
+/// range of `len` keys starting at `lo`, cut at `max`
+fn query_at(lo: u64, len: u64, max: u64) -> (u64, u64) {
+    (lo, lo.saturating_add(len - 1).min(max))
 }
 
-pub fn generate_normal_i32(count: usize, mean: f64, std_dev: f64) -> Vec<i32> {
-    let normal = Normal::new(mean, std_dev).unwrap();
-    let mut rng = thread_rng();
+impl DataGen {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// underlying generator, for drawing anything else from the same seed
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// normal samples clamped to [min, max]
+    fn normal(&mut self, count: usize, mean: f64, std_dev: f64, min: f64, max: f64) -> Vec<f64> {
+        let normal = Normal::new(mean, std_dev).unwrap();
+        (0..count)
+            .map(|_| normal.sample(&mut self.rng).max(min).min(max))
+            .collect()
+    }
+
+    pub fn normal_u64(&mut self, count: usize, mean: f64, std_dev: f64) -> Vec<u64> {
+        let samples = self.normal(count, mean, std_dev, 0.0, u64::MAX as f64);
+        samples.into_iter().map(|sample| sample as u64).collect()
+    }
+
+    pub fn normal_u32(&mut self, count: usize, mean: f64, std_dev: f64) -> Vec<u32> {
+        let samples = self.normal(count, mean, std_dev, 0.0, u32::MAX as f64);
+        samples.into_iter().map(|sample| sample as u32).collect()
+    }
+
+    pub fn normal_i32(&mut self, count: usize, mean: f64, std_dev: f64) -> Vec<i32> {
+        let samples = self.normal(count, mean, std_dev, i32::MIN as f64, i32::MAX as f64);
+        samples.into_iter().map(|sample| sample as i32).collect()
+    }
+
+    pub fn normal_u16(&mut self, count: usize, mean: f64, std_dev: f64) -> Vec<u16> {
+        let samples = self.normal(count, mean, std_dev, 0.0, u16::MAX as f64);
+        samples.into_iter().map(|sample| sample as u16).collect()
+    }
+
+    pub fn normal_u8(&mut self, count: usize, mean: f64, std_dev: f64) -> Vec<u8> {
+        let samples = self.normal(count, mean, std_dev, 0.0, u8::MAX as f64);
+        samples.into_iter().map(|sample| sample as u8).collect()
+    }
+
+    pub fn uniform_u64(&mut self, count: usize, min: u64, max: u64) -> Vec<u64> {
+        let uniform = Uniform::new_inclusive(min, max);
+        (0..count).map(|_| uniform.sample(&mut self.rng)).collect()
+    }
+
+    pub fn uniform_u32(&mut self, count: usize, min: u32, max: u32) -> Vec<u32> {
+        let uniform = Uniform::new_inclusive(min, max);
+        (0..count).map(|_| uniform.sample(&mut self.rng)).collect()
+    }
+
+    pub fn uniform_i32(&mut self, count: usize, min: i32, max: i32) -> Vec<i32> {
+        let uniform = Uniform::new_inclusive(min, max);
+        (0..count).map(|_| uniform.sample(&mut self.rng)).collect()
+    }
+
+    pub fn strings(&mut self, count: usize, min_len: usize, max_len: usize) -> Vec<String> {
+        let len_dist = Uniform::new_inclusive(min_len, max_len);
+
+        (0..count)
+            .map(|_| {
+                let len = len_dist.sample(&mut self.rng);
+                (0..len)
+                    .map(|_| self.rng.gen_range(b'a'..=b'z') as char)
+                    .collect()
+            })
+            .collect()
+    }
+
+    // smooth keys with normal distribution
+    pub fn smooth_u64(&mut self, count: Option<usize>) -> Vec<u64> {
+        let count = count.unwrap_or(DEFAULT_COUNT);
+        let mean = (u64::MAX / 2) as f64;
+        let std_dev = (u64::MAX / 6) as f64;
+        self.normal_u64(count, mean, std_dev)
+    }
+
+    pub fn smooth_u32(&mut self, count: Option<usize>) -> Vec<u32> {
+        let count = count.unwrap_or(DEFAULT_COUNT);
+        let mean = (u32::MAX / 2) as f64;
+        let std_dev = (u32::MAX / 6) as f64;
+        self.normal_u32(count, mean, std_dev)
+    }
+
+    pub fn smooth_i32(&mut self, count: Option<usize>) -> Vec<i32> {
+        let count = count.unwrap_or(DEFAULT_COUNT);
+        let mean = 0.0;
+        let std_dev = (i32::MAX / 3) as f64;
+        self.normal_i32(count, mean, std_dev)
+    }
+
+    pub fn smooth_u16(&mut self, count: Option<usize>) -> Vec<u16> {
+        let count = count.unwrap_or(DEFAULT_COUNT);
+        let mean = (u16::MAX / 2) as f64;
+        let std_dev = (u16::MAX / 6) as f64;
+        self.normal_u16(count, mean, std_dev)
+    }
+
+    pub fn smooth_u8(&mut self, count: Option<usize>) -> Vec<u8> {
+        let count = count.unwrap_or(DEFAULT_COUNT);
+        let mean = (u8::MAX / 2) as f64;
+        let std_dev = (u8::MAX / 6) as f64;
+        self.normal_u8(count, mean, std_dev)
+    }
+
+    /// keys `min + rank - 1` with Zipf distributed ranks in [1, max - min + 1]
+    /// dense right above `min` with a heavy tail, larger exponents are more skewed
+    pub fn zipfian_u64(&mut self, count: usize, min: u64, max: u64, exponent: f64) -> Vec<u64> {
+        let ranks = (max - min).saturating_add(1);
+        let zipf = Zipf::new(ranks, exponent).unwrap();
+        (0..count)
+            .map(|_| {
+                let rank: f64 = zipf.sample(&mut self.rng);
+                min.saturating_add(rank as u64 - 1).min(max)
+            })
+            .collect()
+    }
+
+    /// bursty keys: `num_clusters` cluster starts uniform in [min, max], each key lies
+    /// within `cluster_width` after a random cluster start
+    pub fn clustered_u64(
+        &mut self,
+        count: usize,
+        min: u64,
+        max: u64,
+        num_clusters: usize,
+        cluster_width: u64,
+    ) -> Vec<u64> {
+        let starts = self.uniform_u64(num_clusters.max(1), min, max);
+        (0..count)
+            .map(|_| {
+                let start = starts[self.rng.gen_range(0..starts.len())];
+                start
+                    .saturating_add(self.rng.gen_range(0..=cluster_width))
+                    .min(max)
+            })
+            .collect()
+    }
+
+    /// increasing keys from `start`, like auto-increment ids with deletions: each
+    /// step is 1, or with probability `gap_probability` a gap of up to `max_gap`
+    pub fn sequential_u64(
+        &mut self,
+        count: usize,
+        start: u64,
+        gap_probability: f64,
+        max_gap: u64,
+    ) -> Vec<u64> {
+        let mut key = start;
+        (0..count)
+            .map(|i| {
+                if i > 0 {
+                    let step = if self.rng.gen_bool(gap_probability) {
+                        self.rng.gen_range(1..=max_gap.max(1))
+                    } else {
+                        1
+                    };
+                    key = key.saturating_add(step);
+                }
+                key
+            })
+            .collect()
+    }
+
+    /// adversarial keys in `num_groups` groups, all keys of a group share their top
+    /// 64 - `suffix_bits` bits. groups differ high up while their keys differ only in
+    /// the low bits, so the samples around a group share a short prefix and the
+    /// group's keys collide in the few bits after it
+    pub fn shared_prefix_u64(
+        &mut self,
+        count: usize,
+        num_groups: usize,
+        suffix_bits: u32,
+    ) -> Vec<u64> {
+        let suffix_mask = 1u64
+            .checked_shl(suffix_bits)
+            .map_or(u64::MAX, |bit| bit - 1);
+        let prefixes: Vec<u64> = (0..num_groups.max(1))
+            .map(|_| self.rng.r#gen::<u64>() & !suffix_mask)
+            .collect();
+        (0..count)
+            .map(|_| {
+                let prefix = prefixes[self.rng.gen_range(0..prefixes.len())];
+                prefix | (self.rng.r#gen::<u64>() & suffix_mask)
+            })
+            .collect()
+    }
+
+    /// inclusive range queries starting uniformly in [min, max], cut at `max`
+    pub fn uniform_queries(
+        &mut self,
+        count: usize,
+        min: u64,
+        max: u64,
+        range_len: RangeLenDistribution,
+    ) -> Vec<(u64, u64)> {
+        (0..count)
+            .map(|_| {
+                let len = range_len.sample(&mut self.rng);
+                let lo = self
+                    .rng
+                    .gen_range(min..=max.saturating_sub(len - 1).max(min));
+                query_at(lo, len, max)
+            })
+            .collect()
+    }
+
+    /// point-like queries: single keys or short ranges of up to `max_len` keys
+    pub fn point_queries(
+        &mut self,
+        count: usize,
+        min: u64,
+        max: u64,
+        max_len: u64,
+    ) -> Vec<(u64, u64)> {
+        let range_len = RangeLenDistribution::Uniform {
+            min: 1,
+            max: max_len,
+        };
+        self.uniform_queries(count, min, max, range_len)
+    }
+
+    /// long range queries with lengths spread over [2^min_log2, 2^max_log2] keys
+    pub fn long_queries(
+        &mut self,
+        count: usize,
+        min: u64,
+        max: u64,
+        min_log2: u32,
+        max_log2: u32,
+    ) -> Vec<(u64, u64)> {
+        let range_len = RangeLenDistribution::LogUniform { min_log2, max_log2 };
+        self.uniform_queries(count, min, max, range_len)
+    }
+
+    /// correlated queries starting 1 to `max_distance` keys after a random key
+    /// such queries land in the gaps right next to the keys, where filters are weakest
+    pub fn correlated_queries(
+        &mut self,
+        count: usize,
+        keys: &[u64],
+        max_distance: u64,
+        range_len: RangeLenDistribution,
+    ) -> Vec<(u64, u64)> {
+        if keys.is_empty() {
+            return Vec::new();
+        }
+
+        (0..count)
+            .map(|_| {
+                let key = keys[self.rng.gen_range(0..keys.len())];
+                let lo = key.saturating_add(self.rng.gen_range(1..=max_distance.max(1)));
+                query_at(lo, range_len.sample(&mut self.rng), u64::MAX)
+            })
+            .collect()
+    }
+
+    /// queries holding none of `keys`, placed in the gaps between the smallest and the
+    /// largest key. starts are uniform over the gaps, and a length too long for the gaps
+    /// drawn for it is cut to the last one. returns no queries if the keys leave no gap
+    pub fn empty_queries(
+        &mut self,
+        count: usize,
+        keys: &[u64],
+        range_len: RangeLenDistribution,
+    ) -> Vec<(u64, u64)> {
+        let mut sorted_keys = keys.to_vec();
+        sorted_keys.sort();
+        sorted_keys.dedup();
+        // (first, last) key of every gap
+        let gaps: Vec<(u64, u64)> = sorted_keys
+            .windows(2)
+            .filter(|pair| pair[1] - pair[0] > 1)
+            .map(|pair| (pair[0] + 1, pair[1] - 1))
+            .collect();
+        if gaps.is_empty() {
+            return Vec::new();
+        }
 
-    (0..count)
-        .map(|_| {
-            let sample: f64 = normal.sample(&mut rng);
-            sample.max(i32::MIN as f64).min(i32::MAX as f64) as i32
-        })
-        .collect()
+        // pick gaps by size through the running total of their sizes
+        let mut gap_ends = Vec::with_capacity(gaps.len());
+        let mut total = 0u128;
+        for &(first, last) in &gaps {
+            total += (last - first) as u128 + 1;
+            gap_ends.push(total);
+        }
+
+        (0..count)
+            .map(|_| {
+                let len = range_len.sample(&mut self.rng);
+                // retry a few gaps before cutting the length to the last one drawn
+                let mut gap = gaps[0];
+                for _ in 0..16 {
+                    let position = self.rng.gen_range(0..total);
+                    gap = gaps[gap_ends.partition_point(|&end| end <= position)];
+                    if gap.1 - gap.0 >= len - 1 {
+                        break;
+                    }
+                }
+                let len = len.min(gap.1 - gap.0 + 1);
+                let lo = self.rng.gen_range(gap.0..=gap.1 - (len - 1));
+                query_at(lo, len, gap.1)
+            })
+            .collect()
+    }
 }
 
-pub fn generate_normal_u16(count: usize, mean: f64, std_dev: f64) -> Vec<u16> {
-    let normal = Normal::new(mean, std_dev).unwrap();
-    let mut rng = thread_rng();
+pub fn generate_normal_u64(count: usize, mean: f64, std_dev: f64) -> Vec<u64> {
+    DataGen::from_entropy().normal_u64(count, mean, std_dev)
+}
 
-    (0..count)
-        .map(|_| {
-            let sample: f64 = normal.sample(&mut rng);
-            sample.max(0.0).min(u16::MAX as f64) as u16
-        })
-        .collect()
+pub fn generate_normal_u32(count: usize, mean: f64, std_dev: f64) -> Vec<u32> {
+    DataGen::from_entropy().normal_u32(count, mean, std_dev)
 }
 
-pub fn generate_normal_u8(count: usize, mean: f64, std_dev: f64) -> Vec<u8> {
-    let normal = Normal::new(mean, std_dev).unwrap();
-    let mut rng = thread_rng();
+pub fn generate_normal_i32(count: usize, mean: f64, std_dev: f64) -> Vec<i32> {
+    DataGen::from_entropy().normal_i32(count, mean, std_dev)
+}
 
-    (0..count)
-        .map(|_| {
-            let sample: f64 = normal.sample(&mut rng);
-            sample.max(0.0).min(u8::MAX as f64) as u8
-        })
-        .collect()
+pub fn generate_normal_u16(count: usize, mean: f64, std_dev: f64) -> Vec<u16> {
+    DataGen::from_entropy().normal_u16(count, mean, std_dev)
 }
 
-pub fn generate_uniform_u64(count: usize, min: u64, max: u64) -> Vec<u64> {
-    let uniform = Uniform::new_inclusive(min, max);
-    let mut rng = thread_rng();
+pub fn generate_normal_u8(count: usize, mean: f64, std_dev: f64) -> Vec<u8> {
+    DataGen::from_entropy().normal_u8(count, mean, std_dev)
+}
 
-    (0..count).map(|_| uniform.sample(&mut rng)).collect()
+pub fn generate_uniform_u64(count: usize, min: u64, max: u64) -> Vec<u64> {
+    DataGen::from_entropy().uniform_u64(count, min, max)
 }
 
 pub fn generate_uniform_u32(count: usize, min: u32, max: u32) -> Vec<u32> {
-    let uniform = Uniform::new_inclusive(min, max);
-    let mut rng = thread_rng();
-
-    (0..count).map(|_| uniform.sample(&mut rng)).collect()
+    DataGen::from_entropy().uniform_u32(count, min, max)
 }
 
 pub fn generate_uniform_i32(count: usize, min: i32, max: i32) -> Vec<i32> {
-    let uniform = Uniform::new_inclusive(min, max);
-    let mut rng = thread_rng();
-
-    (0..count).map(|_| uniform.sample(&mut rng)).collect()
+    DataGen::from_entropy().uniform_i32(count, min, max)
 }
 
 pub fn generate_strings(count: usize, min_len: usize, max_len: usize) -> Vec<String> {
-    let mut rng = thread_rng();
-    let len_dist = Uniform::new_inclusive(min_len, max_len);
-
-    (0..count)
-        .map(|_| {
-            let len = len_dist.sample(&mut rng);
-            (0..len)
-                .map(|_| rng.gen_range(b'a'..=b'z') as char)
-                .collect()
-        })
-        .collect()
+    DataGen::from_entropy().strings(count, min_len, max_len)
 }
 
 // generate smooth u64 keys with normal distribution
 pub fn generate_smooth_u64(count: Option<usize>) -> Vec<u64> {
-    let count = count.unwrap_or(DEFAULT_COUNT);
-    let mean = (u64::MAX / 2) as f64;
-    let std_dev = (u64::MAX / 6) as f64;
-    generate_normal_u64(count, mean, std_dev)
+    DataGen::from_entropy().smooth_u64(count)
 }
 
 pub fn generate_smooth_u32(count: Option<usize>) -> Vec<u32> {
-    let count = count.unwrap_or(DEFAULT_COUNT);
-    let mean = (u32::MAX / 2) as f64;
-    let std_dev = (u32::MAX / 6) as f64;
-    generate_normal_u32(count, mean, std_dev)
+    DataGen::from_entropy().smooth_u32(count)
 }
 
 pub fn generate_smooth_i32(count: Option<usize>) -> Vec<i32> {
-    let count = count.unwrap_or(DEFAULT_COUNT);
-    let mean = 0.0;
-    let std_dev = (i32::MAX / 3) as f64;
-    generate_normal_i32(count, mean, std_dev)
+    DataGen::from_entropy().smooth_i32(count)
 }
 
 pub fn generate_smooth_u16(count: Option<usize>) -> Vec<u16> {
-    let count = count.unwrap_or(DEFAULT_COUNT);
-    let mean = (u16::MAX / 2) as f64;
-    let std_dev = (u16::MAX / 6) as f64;
-    generate_normal_u16(count, mean, std_dev)
+    DataGen::from_entropy().smooth_u16(count)
 }
 
 pub fn generate_smooth_u8(count: Option<usize>) -> Vec<u8> {
-    let count = count.unwrap_or(DEFAULT_COUNT);
-    let mean = (u8::MAX / 2) as f64;
-    let std_dev = (u8::MAX / 6) as f64;
-    generate_normal_u8(count, mean, std_dev)
+    DataGen::from_entropy().smooth_u8(count)
+}
+
+/// inclusive range queries starting uniformly in [min, max], cut at `max`
+pub fn generate_uniform_queries(
+    count: usize,
+    min: u64,
+    max: u64,
+    range_len: RangeLenDistribution,
+) -> Vec<(u64, u64)> {
+    DataGen::from_entropy().uniform_queries(count, min, max, range_len)
+}
+
+/// point-like queries: single keys or short ranges of up to `max_len` keys
+pub fn generate_point_queries(count: usize, min: u64, max: u64, max_len: u64) -> Vec<(u64, u64)> {
+    DataGen::from_entropy().point_queries(count, min, max, max_len)
+}
+
+/// long range queries with lengths spread over [2^min_log2, 2^max_log2] keys
+pub fn generate_long_queries(
+    count: usize,
+    min: u64,
+    max: u64,
+    min_log2: u32,
+    max_log2: u32,
+) -> Vec<(u64, u64)> {
+    DataGen::from_entropy().long_queries(count, min, max, min_log2, max_log2)
+}
+
+/// correlated queries starting 1 to `max_distance` keys after a random key
+pub fn generate_correlated_queries(
+    count: usize,
+    keys: &[u64],
+    max_distance: u64,
+    range_len: RangeLenDistribution,
+) -> Vec<(u64, u64)> {
+    DataGen::from_entropy().correlated_queries(count, keys, max_distance, range_len)
+}
+
+/// queries holding none of `keys`, see `DataGen::empty_queries`
+pub fn generate_empty_queries(
+    count: usize,
+    keys: &[u64],
+    range_len: RangeLenDistribution,
+) -> Vec<(u64, u64)> {
+    DataGen::from_entropy().empty_queries(count, keys, range_len)
 }
 
 #[cfg(test)]
@@ -142,33 +480,33 @@ mod tests {
 
     #[test]
     fn test_smooth_u64_default() {
-        let data = generate_smooth_u64(None);
+        let data = DataGen::new(42).smooth_u64(None);
         assert_eq!(data.len(), DEFAULT_COUNT);
     }
 
     #[test]
     fn test_smooth_u64_custom_count() {
-        let data = generate_smooth_u64(Some(1000));
+        let data = DataGen::new(42).smooth_u64(Some(1000));
         assert_eq!(data.len(), 1000);
     }
 
     #[test]
     fn test_normal_u64_bounds() {
-        let data = generate_normal_u64(1000, 100.0, 10.0);
+        let data = DataGen::new(42).normal_u64(1000, 100.0, 10.0);
         assert_eq!(data.len(), 1000);
         assert!(data.iter().all(|&x| x < u64::MAX));
     }
 
     #[test]
     fn test_uniform_u64() {
-        let data = generate_uniform_u64(1000, 0, 1000);
+        let data = DataGen::new(42).uniform_u64(1000, 0, 1000);
         assert_eq!(data.len(), 1000);
         assert!(data.iter().all(|&x| x <= 1000));
     }
 
     #[test]
     fn test_strings() {
-        let data = generate_strings(100, 5, 10);
+        let data = DataGen::new(42).strings(100, 5, 10);
         assert_eq!(data.len(), 100);
         assert!(data.iter().all(|s| s.len() >= 5 && s.len() <= 10));
         assert!(
@@ -176,4 +514,124 @@ mod tests {
                 .all(|s| s.chars().all(|c| c.is_ascii_lowercase()))
         );
     }
+
+    #[test]
+    fn test_uniform_queries() {
+        let mut data_gen = DataGen::new(42);
+        let queries = data_gen.uniform_queries(1000, 100, 10_000, RangeLenDistribution::Fixed(50));
+        assert_eq!(queries.len(), 1000);
+        assert!(
+            queries
+                .iter()
+                .all(|&(lo, hi)| lo >= 100 && hi - lo == 49 && hi <= 10_000)
+        );
+
+        let queries = data_gen.point_queries(1000, 0, u64::MAX, 4);
+        assert!(queries.iter().all(|&(lo, hi)| hi - lo < 4));
+        assert!(queries.iter().any(|&(lo, hi)| lo == hi));
+
+        let queries = data_gen.long_queries(1000, 0, u64::MAX, 20, 40);
+        assert!(
+            queries
+                .iter()
+                .all(|&(lo, hi)| (1 << 20) - 1 <= hi - lo && hi - lo < 1 << 40)
+        );
+        assert!(
+            queries
+                .iter()
+                .all(|&(lo, hi)| (hi - lo + 1).is_power_of_two())
+        );
+
+        // lengths past the key range are cut
+        let queries = data_gen.uniform_queries(100, 0, 10, RangeLenDistribution::Fixed(1000));
+        assert!(queries.iter().all(|&(lo, hi)| lo == 0 && hi == 10));
+    }
+
+    #[test]
+    fn test_correlated_queries() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(1000, 0, 1 << 40);
+        let length = RangeLenDistribution::Uniform { min: 1, max: 8 };
+        let queries = data_gen.correlated_queries(1000, &keys, 16, length);
+        let key_set: std::collections::BTreeSet<u64> = keys.iter().copied().collect();
+        for &(lo, hi) in &queries {
+            let key = *key_set.range(..lo).next_back().unwrap();
+            assert!(lo - key <= 16);
+            assert!(hi >= lo && hi - lo < 8);
+        }
+        assert!(data_gen.correlated_queries(10, &[], 16, length).is_empty());
+    }
+
+    #[test]
+    fn test_empty_queries() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(2000, 0, 1 << 20);
+        let key_set: std::collections::BTreeSet<u64> = keys.iter().copied().collect();
+        let length = RangeLenDistribution::LogUniform {
+            min_log2: 0,
+            max_log2: 12,
+        };
+        let queries = data_gen.empty_queries(5000, &keys, length);
+        assert_eq!(queries.len(), 5000);
+        for &(lo, hi) in &queries {
+            assert!(lo <= hi);
+            assert!(key_set.range(lo..=hi).next().is_none(), "[{}, {}]", lo, hi);
+            assert!(*key_set.first().unwrap() < lo && hi < *key_set.last().unwrap());
+        }
+        assert!(queries.iter().any(|&(lo, hi)| hi - lo >= 100));
+
+        assert!(data_gen.empty_queries(10, &[1, 2, 3], length).is_empty());
+    }
+
+    #[test]
+    fn test_seeded_reproducible() {
+        let mut first = DataGen::new(7);
+        let mut second = DataGen::new(7);
+        assert_eq!(
+            first.normal_u64(100, 1e6, 1e3),
+            second.normal_u64(100, 1e6, 1e3)
+        );
+        assert_eq!(first.strings(20, 1, 8), second.strings(20, 1, 8));
+        let range_len = RangeLenDistribution::LogUniform {
+            min_log2: 0,
+            max_log2: 30,
+        };
+        assert_eq!(
+            first.uniform_queries(100, 0, u64::MAX, range_len),
+            second.uniform_queries(100, 0, u64::MAX, range_len)
+        );
+        assert_ne!(
+            DataGen::new(7).uniform_u64(100, 0, u64::MAX),
+            DataGen::new(8).uniform_u64(100, 0, u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_skewed_distributions() {
+        let mut data_gen = DataGen::new(1);
+
+        // most zipfian keys sit right above min
+        let keys = data_gen.zipfian_u64(10_000, 1000, u64::MAX, 1.2);
+        assert!(keys.iter().all(|&key| key >= 1000));
+        assert!(keys.iter().filter(|&&key| key < 1100).count() > 5000);
+        assert!(keys.iter().any(|&key| key > 1 << 20));
+
+        let keys = data_gen.clustered_u64(10_000, 0, 1 << 40, 8, 1000);
+        let mut starts: Vec<u64> = keys.iter().map(|key| key / 1_000_000).collect();
+        starts.sort();
+        starts.dedup();
+        assert!(starts.len() <= 16);
+
+        let keys = data_gen.sequential_u64(10_000, 500, 0.01, 1 << 20);
+        assert_eq!(keys[0], 500);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        let gaps = keys.windows(2).filter(|pair| pair[1] - pair[0] > 1).count();
+        assert!(gaps > 50 && gaps < 200);
+
+        let keys = data_gen.shared_prefix_u64(10_000, 4, 12);
+        let mut prefixes: Vec<u64> = keys.iter().map(|key| key >> 12).collect();
+        prefixes.sort();
+        prefixes.dedup();
+        assert!(prefixes.len() <= 4);
+    }
 }
@@ -0,0 +1,184 @@
+use crate::UnsignedKey;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+// keys allocated up front, a corrupted count must not reserve more
+const MAX_PREALLOCATED_KEYS: u64 = 1 << 24;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// check that keys never decrease, duplicates are allowed
+fn check_sorted<K: UnsignedKey>(keys: &[K]) -> io::Result<()> {
+    match keys.windows(2).position(|pair| pair[0] > pair[1]) {
+        Some(index) => Err(invalid_data(format!(
+            "key {} at index {} is smaller than the key before it",
+            keys[index + 1],
+            index + 1
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Read a SOSD binary dataset: [count: u64] [keys: count * K], little-endian
+///
+/// Fails with `UnexpectedEof` if the input holds fewer keys than its count, and with
+/// `InvalidData` if bytes follow the last key or the keys are not sorted.
+pub fn read_sosd<K: UnsignedKey>(reader: &mut impl Read) -> io::Result<Vec<K>> {
+    let mut count_bytes = [0u8; 8];
+    reader.read_exact(&mut count_bytes)?;
+    let count = u64::from_le_bytes(count_bytes);
+
+    let mut keys = Vec::with_capacity(count.min(MAX_PREALLOCATED_KEYS) as usize);
+    for _ in 0..count {
+        keys.push(K::read_le(reader)?);
+    }
+    if reader.read(&mut [0u8])? != 0 {
+        return Err(invalid_data(format!(
+            "data continues after the {} keys of the header count",
+            count
+        )));
+    }
+
+    check_sorted(&keys)?;
+    Ok(keys)
+}
+
+/// Write keys as a SOSD binary dataset, see `read_sosd`
+pub fn write_sosd<K: UnsignedKey>(writer: &mut impl Write, keys: &[K]) -> io::Result<()> {
+    writer.write_all(&(keys.len() as u64).to_le_bytes())?;
+    for &key in keys {
+        key.write_le(writer)?;
+    }
+    Ok(())
+}
+
+/// Read a text dataset with one decimal key per line
+///
+/// Surrounding whitespace and blank lines are skipped. Fails with `InvalidData` on a
+/// line that is not a key, or if the keys are not sorted.
+pub fn read_text<K: UnsignedKey + FromStr>(reader: impl BufRead) -> io::Result<Vec<K>> {
+    let mut keys = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let key = line
+            .parse()
+            .map_err(|_| invalid_data(format!("line {} is not a key: {:?}", index + 1, line)))?;
+        keys.push(key);
+    }
+
+    check_sorted(&keys)?;
+    Ok(keys)
+}
+
+pub fn load_sosd_u64(path: impl AsRef<Path>) -> io::Result<Vec<u64>> {
+    read_sosd(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn load_sosd_u32(path: impl AsRef<Path>) -> io::Result<Vec<u32>> {
+    read_sosd(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn load_text_u64(path: impl AsRef<Path>) -> io::Result<Vec<u64>> {
+    read_text(BufReader::new(File::open(path)?))
+}
+
+pub fn load_text_u32(path: impl AsRef<Path>) -> io::Result<Vec<u32>> {
+    read_text(BufReader::new(File::open(path)?))
+}
+
+pub fn save_sosd<K: UnsignedKey>(path: impl AsRef<Path>, keys: &[K]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_sosd(&mut writer, keys)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diva;
+    use crate::data_gen::DataGen;
+
+    fn sosd_bytes<K: UnsignedKey>(keys: &[K]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_sosd(&mut bytes, keys).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_sosd_round_trip() {
+        let mut keys = DataGen::new(5).uniform_u64(1000, 0, u64::MAX);
+        keys.sort();
+        let bytes = sosd_bytes(&keys);
+        assert_eq!(bytes.len(), 8 + 8 * 1000);
+        assert_eq!(&bytes[..8], &1000u64.to_le_bytes());
+        assert_eq!(read_sosd::<u64>(&mut bytes.as_slice()).unwrap(), keys);
+
+        let small: Vec<u32> = vec![1, 1, 7, u32::MAX];
+        let bytes = sosd_bytes(&small);
+        assert_eq!(bytes.len(), 8 + 4 * 4);
+        assert_eq!(read_sosd::<u32>(&mut bytes.as_slice()).unwrap(), small);
+
+        // through a file, straight into a filter
+        let path = std::env::temp_dir().join(format!("sosd_test_{}", std::process::id()));
+        save_sosd(&path, &keys).unwrap();
+        let loaded = load_sosd_u64(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let diva = Diva::new_with_keys(&loaded, 128, 0.01);
+        assert!(keys.iter().all(|&key| diva.may_contain(key)));
+    }
+
+    #[test]
+    fn test_sosd_invalid() {
+        let bytes = sosd_bytes(&[1u64, 2, 3]);
+
+        // fewer keys than the count, and more data than the count
+        let error = read_sosd::<u64>(&mut &bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let error = read_sosd::<u64>(&mut &bytes[..5]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let mut longer = bytes.clone();
+        longer.push(0);
+        let error = read_sosd::<u64>(&mut longer.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // a huge count fails on the missing keys without allocating them
+        let mut huge = bytes.clone();
+        huge[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = read_sosd::<u64>(&mut huge.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let unsorted = sosd_bytes(&[1u64, 3, 2]);
+        let error = read_sosd::<u64>(&mut unsorted.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("index 2"));
+
+        // u64 keys read as u32 keys leave data behind
+        let error = read_sosd::<u32>(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_text() {
+        let text = "1\n  20\n\n300\r\n300\n5000000000\n";
+        let keys: Vec<u64> = read_text(text.as_bytes()).unwrap();
+        assert_eq!(keys, vec![1, 20, 300, 300, 5_000_000_000]);
+
+        let error = read_text::<u32>(text.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 6"));
+
+        let error = read_text::<u64>("5\n4\n".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = read_text::<u64>("5\nx\n".as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+        assert!(read_text::<u64>("".as_bytes()).unwrap().is_empty());
+    }
+}
@@ -1,24 +1,45 @@
 use crate::Key;
+use crate::RangeFilter;
 use crate::U64_BITS;
-use crate::infix_store::InfixStore;
+use crate::UnsignedKey;
+use crate::Unsupported;
+use crate::infix_store::{
+    InfixStore, InfixStoreError, MAX_ELEMENTS, MAX_QUOTIENT_SIZE, MIN_QUOTIENT_SIZE,
+};
+use crate::key_encoder::KeyEncoder;
+use crate::memory::MemoryUsage;
 use crate::utils::longest_common_prefix_length;
 use crate::y_fast_trie::YFastTrie;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem::size_of;
 
-const BASE_IMPLICIT_SIZE: u32 = 10;
+const SERIALIZATION_MAGIC: [u8; 4] = *b"DIVA";
+const SERIALIZATION_VERSION: u16 = 1;
+// remainder sizes tried by `plan_for_budget`, 2 bits keep the point FPR below 1/2
+const MIN_BUDGET_REMAINDER_SIZE: u8 = 2;
+const MAX_BUDGET_REMAINDER_SIZE: u8 = 32;
+// shortened infixes need 2 remainder bits, and a full-length infix of the
+// largest quotient size must fit in a u64
+const MIN_REMAINDER_SIZE: u8 = 2;
+const MAX_REMAINDER_SIZE: u8 = U64_BITS as u8 - MAX_QUOTIENT_SIZE;
 
 /// Diva range filter
 ///
+/// Generic over the key width: `u16`, `u32`, `u64` (the default) or `u128` keys.
+///
 /// # Arguments
 /// * `y_fast_trie` - Y-Fast Trie
 /// * `target_size` - Target size
 /// * `fpr` - False positive rate
 /// * `remainder_size` - Remainder size
+/// * `use_redundant_bits` - Whether infixes skip the bits implied by their bucket's samples
 ///
 /// # Example
 /// ```rust
 /// use range_filters::diva::Diva;
-/// let keys = vec![1, 2, 3, 4, 5];
+/// let keys: Vec<u64> = vec![1, 2, 3, 4, 5];
 /// let target_size = 1024;
 /// let fpr = 0.01;
 /// let diva = Diva::new_with_keys(&keys, target_size, fpr);
@@ -26,32 +47,193 @@ const BASE_IMPLICIT_SIZE: u32 = 10;
 ///
 /// # Returns
 /// * `Diva` - Diva range filter
-pub struct Diva {
-    y_fast_trie: YFastTrie,
+pub struct Diva<K: UnsignedKey = Key> {
+    y_fast_trie: YFastTrie<K>,
     target_size: usize,
     fpr: f64,
     remainder_size: u8,
+    use_redundant_bits: bool,
+}
+
+/// Diva construction parameters
+///
+/// # Arguments
+/// * `target_size` - Target number of keys between consecutive samples
+/// * `fpr` - False positive rate
+/// * `use_redundant_bits` - Skip the bits after the MSB that every key between two
+///   samples shares, spending them on key bits instead (default: true)
+/// * `remainder_size` - Remainder size, derived from `fpr` when None (default: None)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivaConfig {
+    pub target_size: usize,
+    pub fpr: f64,
+    pub use_redundant_bits: bool,
+    pub remainder_size: Option<u8>,
+}
+
+impl Default for DivaConfig {
+    fn default() -> Self {
+        Self {
+            target_size: 1024,
+            fpr: 0.01,
+            use_redundant_bits: true,
+            remainder_size: None,
+        }
+    }
+}
+
+/// Memory budget of a Diva, counted like `Diva::size_in_bits`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryBudget {
+    BitsPerKey(f64),
+    TotalBytes(usize),
+}
+
+/// Diva parameters chosen for a memory budget by `Diva::plan_for_budget`
+///
+/// # Arguments
+/// * `config` - Construction parameters, with an explicit remainder size
+/// * `quotient_size` - Quotient size of the infix store of a full bucket
+/// * `bits_per_key` - Expected size of the filter per key
+/// * `expected_fpr` - Expected FPR of empty range queries of the expected length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivaPlan {
+    pub config: DivaConfig,
+    pub quotient_size: u8,
+    pub bits_per_key: f64,
+    pub expected_fpr: f64,
+}
+
+/// Structure summary of a Diva, see `Diva::stats`
+///
+/// # Arguments
+/// * `config` - Construction parameters, with the remainder size in use
+/// * `sample_count` - Number of samples in the y-fast trie
+/// * `infix_store_count` - Number of buckets with an infix store
+/// * `infix_count` - Number of infixes in all infix stores
+/// * `max_bucket_size` - Most infixes in one infix store
+/// * `slot_count` - Slots allocated in all infix stores
+/// * `quotient_sizes` - Number of infix stores per quotient size, sorted by size
+/// * `size_in_bits` - Size of the filter, see `Diva::size_in_bits`
+/// * `memory` - Estimated memory used by the filter, see `Diva::memory_usage`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DivaStats {
+    pub config: DivaConfig,
+    pub sample_count: usize,
+    pub infix_store_count: usize,
+    pub infix_count: usize,
+    pub max_bucket_size: usize,
+    pub slot_count: usize,
+    pub quotient_sizes: Vec<(u8, usize)>,
+    pub size_in_bits: usize,
+    pub memory: MemoryUsage,
+}
+
+impl DivaStats {
+    /// samples plus infixes
+    pub fn key_count(&self) -> usize {
+        self.sample_count + self.infix_count
+    }
+
+    /// infixes per allocated slot, 0 without infix stores
+    pub fn load_factor(&self) -> f64 {
+        if self.slot_count == 0 {
+            return 0.0;
+        }
+        self.infix_count as f64 / self.slot_count as f64
+    }
+
+    pub fn bits_per_key(&self) -> f64 {
+        if self.key_count() == 0 {
+            return 0.0;
+        }
+        self.size_in_bits as f64 / self.key_count() as f64
+    }
+}
+
+impl fmt::Display for DivaStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Configuration:")?;
+        writeln!(f, "  Target size (T):      {}", self.config.target_size)?;
+        writeln!(f, "  False positive rate:  {:.4}%", self.config.fpr * 100.0)?;
+        if let Some(remainder_size) = self.config.remainder_size {
+            writeln!(f, "  Remainder size:       {} bits", remainder_size)?;
+        }
+        writeln!(
+            f,
+            "  Redundant bits:       {}",
+            self.config.use_redundant_bits
+        )?;
+        writeln!(f, "Structure:")?;
+        writeln!(f, "  Keys:                 {}", self.key_count())?;
+        writeln!(f, "  Samples:              {}", self.sample_count)?;
+        writeln!(f, "  Infix stores:         {}", self.infix_store_count)?;
+        writeln!(f, "  Infixes:              {}", self.infix_count)?;
+        writeln!(f, "  Largest bucket:       {}", self.max_bucket_size)?;
+        writeln!(f, "  Load factor:          {:.3}", self.load_factor())?;
+        for &(quotient_size, count) in &self.quotient_sizes {
+            let label = format!("Stores with q = {}:", quotient_size);
+            writeln!(f, "  {:<22}{}", label, count)?;
+        }
+        writeln!(f, "Size:")?;
+        writeln!(f, "  Size in bits:         {}", self.size_in_bits)?;
+        writeln!(f, "  Bits per key:         {:.2}", self.bits_per_key())?;
+        writeln!(f, "Memory:")?;
+        write!(f, "{}", self.memory)
+    }
 }
 
-impl Diva {
+impl<K: UnsignedKey> Diva<K> {
+    /// Create an empty filter
+    ///
+    /// # Panics
+    /// If `target_size` is 0 or above `MAX_ELEMENTS`, the most infixes a store holds.
     pub fn new(target_size: usize, fpr: f64) -> Self {
+        Self::check_target_size(target_size);
         let remainder_size = Self::choose_remainder_size(target_size, fpr);
-        const NO_LEVELS: usize = 64;
         Self {
-            y_fast_trie: YFastTrie::new(NO_LEVELS),
+            y_fast_trie: YFastTrie::new(K::BITS),
             target_size,
             fpr,
             remainder_size,
+            use_redundant_bits: DivaConfig::default().use_redundant_bits,
         }
     }
 
-    pub fn new_with_keys(keys: &[Key], target_size: usize, fpr: f64) -> Self {
-        let remainder_size = Self::choose_remainder_size(target_size, fpr);
+    pub fn new_with_keys(keys: &[K], target_size: usize, fpr: f64) -> Self {
+        let config = DivaConfig {
+            target_size,
+            fpr,
+            ..DivaConfig::default()
+        };
+        Self::new_with_config(keys, &config)
+    }
+
+    /// Build a filter over a set of keys
+    ///
+    /// # Panics
+    /// If `config.target_size` is 0 or above `MAX_ELEMENTS`, the most infixes a store
+    /// holds: buckets must split before their store runs out of slots.
+    /// If `config.remainder_size` is below 2 or above 48: shortened infixes need 2
+    /// remainder bits, and quotient plus remainder must fit in 64 bits.
+    pub fn new_with_config(keys: &[K], config: &DivaConfig) -> Self {
+        let DivaConfig {
+            target_size,
+            fpr,
+            use_redundant_bits,
+            remainder_size,
+        } = *config;
+        Self::check_target_size(target_size);
+        if let Some(remainder_size) = remainder_size {
+            Self::check_remainder_size(remainder_size);
+        }
+        let remainder_size =
+            remainder_size.unwrap_or_else(|| Self::choose_remainder_size(target_size, fpr));
         let mut sorted_keys = keys.to_vec();
         sorted_keys.sort();
         sorted_keys.dedup();
 
-        let mut sampled_keys: Vec<Key> = sorted_keys.iter().step_by(target_size).copied().collect();
+        let mut sampled_keys: Vec<K> = sorted_keys.iter().step_by(target_size).copied().collect();
 
         // ensure last key is sampled if not already
         if let Some(&last_key) = sorted_keys.last() {
@@ -60,314 +242,2510 @@ impl Diva {
             }
         }
 
-        // TODO: make this dynamic based on the key length
-        const NO_LEVELS: usize = U64_BITS;
-        let mut y_fast_trie = YFastTrie::new_with_keys(&sampled_keys, NO_LEVELS);
+        // one trie level per key bit
+        let mut y_fast_trie = YFastTrie::new_with_keys(&sampled_keys, K::BITS);
 
         // for each pair of consecutive samples, extract infixes from intermediate keys
         for i in 0..sampled_keys.len().saturating_sub(1) {
             let predecessor = sampled_keys[i];
             let successor = sampled_keys[i + 1];
 
-            // compute extraction parameters from boundary keys
-            let (shared_prefix_len, redundant_bits, quotient_bits) =
-                Self::get_shared_ignore_implicit_size(&predecessor, &successor, false);
-
             // find intermediate keys between these samples excluding the samples themselves
-            let intermediate_keys: Vec<Key> = sorted_keys
+            let intermediate_keys: Vec<K> = sorted_keys
                 .iter()
                 .filter(|&&k| k > predecessor && k < successor)
                 .copied()
                 .collect();
 
+            // the quotient size follows the density of the bucket
+            let quotient_size = InfixStore::choose_quotient_size(intermediate_keys.len());
+
             // extract infixes from intermediate keys
             let mut infixes = Vec::new();
             for key in intermediate_keys {
-                let key_msb = Self::get_msb(&predecessor, &key);
-                let infix = Self::extract_partial_key(
+                let infix = Self::extract_infix(
                     key,
-                    shared_prefix_len,
-                    redundant_bits,
-                    quotient_bits,
+                    predecessor,
+                    successor,
+                    quotient_size,
                     remainder_size,
-                    key_msb,
+                    use_redundant_bits,
                 );
                 infixes.push(infix);
             }
 
             // create InfixStore and attach to predecessor sample
             if !infixes.is_empty() {
-                let infix_store = InfixStore::new_with_infixes(&infixes, remainder_size);
+                let infix_store =
+                    InfixStore::new_with_quotient_size(&infixes, quotient_size, remainder_size);
                 y_fast_trie.set_infix_store(predecessor, infix_store);
             }
         }
 
-        Self {
+        let diva = Self {
             y_fast_trie,
             target_size,
             fpr,
             remainder_size,
+            use_redundant_bits,
+        };
+        if cfg!(debug_assertions)
+            && let Err(error) = diva.validate_infix_stores()
+        {
+            panic!("corrupted infix store after construction: {}", error);
         }
+        diva
     }
 
-    /// compute redundant bits after first differing bit
-    /// redundant bits are consecutive bits with opposite patterns in pred/succ
-    /// that can be reconstructed knowing the key is in this range
-    fn compute_redundant_bits(key_1: Key, key_2: Key, shared_prefix_len: u8) -> u8 {
-        if shared_prefix_len >= 63 {
-            return 0;
-        }
+    pub(crate) fn check_target_size(target_size: usize) {
+        assert!(
+            (1..=MAX_ELEMENTS).contains(&target_size),
+            "target size {} is not in 1..={}",
+            target_size,
+            MAX_ELEMENTS
+        );
+    }
 
-        let mut redundant_bits = 0u8;
+    fn check_remainder_size(remainder_size: u8) {
+        assert!(
+            (MIN_REMAINDER_SIZE..=MAX_REMAINDER_SIZE).contains(&remainder_size),
+            "remainder size {} is not in {}..={}",
+            remainder_size,
+            MIN_REMAINDER_SIZE,
+            MAX_REMAINDER_SIZE
+        );
+    }
 
-        // start after shared prefix + 1 (skip first differing bit)
-        let start_pos = shared_prefix_len + 1;
+    /// Build a filter that fits a memory budget, see `plan_for_budget`
+    /// returns None if no setup fits the budget
+    pub fn new_with_budget(
+        keys: &[K],
+        budget: MemoryBudget,
+        expected_range_len: K,
+    ) -> Option<Self> {
+        let plan = Self::plan_for_budget(keys, budget, expected_range_len)?;
+        Some(Self::new_with_config(keys, &plan.config))
+    }
 
-        for bit_pos in start_pos..64 {
-            let shift = 63 - bit_pos;
-            let bit_1 = (key_1 >> shift) & 1;
-            let bit_2 = (key_2 >> shift) & 1;
+    /// Choose target size, remainder size and quotient size together for a memory budget
+    ///
+    /// Every target size a store can hold is tried with the largest remainder that fits
+    /// the budget, and the one with the lowest expected FPR for ranges of
+    /// `expected_range_len` keys wins. A bucket's quotient size follows its density, so
+    /// it is set by the target size: fewer keys per quotient buy a lower FPR with
+    /// occupieds bits instead of remainder bits. Returns None if no setup fits the budget.
+    ///
+    /// # Arguments
+    /// * `keys` - Keys the filter will be built from, only their count and span are used
+    /// * `budget` - Memory budget
+    /// * `expected_range_len` - Expected number of keys covered by a query, 1 for points
+    pub fn plan_for_budget(
+        keys: &[K],
+        budget: MemoryBudget,
+        expected_range_len: K,
+    ) -> Option<DivaPlan> {
+        let num_keys = keys.len().max(1);
+        let bits_per_key_budget = match budget {
+            MemoryBudget::BitsPerKey(bits_per_key) => bits_per_key,
+            MemoryBudget::TotalBytes(bytes) => (bytes * 8) as f64 / num_keys as f64,
+        };
+        let min_key = keys.iter().copied().min().unwrap_or(K::ZERO);
+        let max_key = keys.iter().copied().max().unwrap_or(K::ZERO);
+        let key_gap = ((max_key.to_f64() - min_key.to_f64()) / num_keys as f64).max(1.0);
 
-            // redundant if pred has 0 and succ has 1 (opposite of first diff bit)
-            if bit_1 == 0 && bit_2 == 1 {
-                redundant_bits += 1;
-            } else {
-                break; // stop at first non-redundant bit
+        let mut best: Option<DivaPlan> = None;
+        // a bucket holds one infix more than its target size right before it splits
+        for target_size in 2..MAX_ELEMENTS {
+            let num_infixes = target_size - 1;
+            let quotient_size = InfixStore::choose_quotient_size(num_infixes);
+            let bits_per_key = |remainder_size: u8| {
+                let store_words =
+                    InfixStore::size_in_words(num_infixes, quotient_size, remainder_size);
+                (K::BITS + store_words * U64_BITS) as f64 / target_size as f64
+            };
+            let Some(remainder_size) = (MIN_BUDGET_REMAINDER_SIZE..=MAX_BUDGET_REMAINDER_SIZE)
+                .rev()
+                .find(|&remainder_size| bits_per_key(remainder_size) <= bits_per_key_budget)
+            else {
+                continue;
+            };
+
+            let expected_fpr = Self::expected_bucket_fpr(
+                num_infixes,
+                quotient_size,
+                remainder_size,
+                target_size as f64 * key_gap,
+                expected_range_len.to_f64(),
+            );
+            if best.is_none_or(|best| expected_fpr < best.expected_fpr) {
+                best = Some(DivaPlan {
+                    config: DivaConfig {
+                        target_size,
+                        // point FPR the remainder size stands for, see `choose_remainder_size`
+                        fpr: 2f64.powi(1 - remainder_size as i32),
+                        remainder_size: Some(remainder_size),
+                        ..DivaConfig::default()
+                    },
+                    quotient_size,
+                    bits_per_key: bits_per_key(remainder_size),
+                    expected_fpr,
+                });
             }
         }
-
-        redundant_bits
+        best
     }
 
-    /// compute shared prefix, redundant bits, and quotient size
-    /// returns: (shared_prefix_len, redundant_bits, quotient_bits)
-    fn get_shared_ignore_implicit_size(
-        key_1: &Key,
-        key_2: &Key,
-        use_redundant_bits: bool,
-    ) -> (u8, u8, u8) {
-        // step 1: find shared prefix length (LCP)
-        let shared = longest_common_prefix_length(*key_1, *key_2) as u8;
+    /// expected FPR of an empty query of `range_len` keys inside a bucket of `bucket_span`
+    /// keys. the infix bits split the aligned window the samples share, about twice the
+    /// bucket span after the redundant bits, into cells. a query matches when it comes
+    /// within a cell of an infix, and is a false positive when it holds no key itself
+    fn expected_bucket_fpr(
+        num_infixes: usize,
+        quotient_size: u8,
+        remainder_size: u8,
+        bucket_span: f64,
+        range_len: f64,
+    ) -> f64 {
+        let cells = 2f64.powi((quotient_size + remainder_size) as i32 - 1);
+        let cell_width = (2.0 * bucket_span / cells).max(1.0);
+        let matching = Self::hit_fraction(num_infixes, bucket_span, cell_width + range_len - 1.0);
+        let true_positives = Self::hit_fraction(num_infixes, bucket_span, range_len);
+        Self::false_positive_share(matching, true_positives)
+    }
 
-        // step 2: compute redundant bits
-        let redundant_bits = if use_redundant_bits {
-            Self::compute_redundant_bits(*key_1, *key_2, shared)
-        } else {
-            0
-        };
+    /// fraction of the queries of `range_len` keys starting in a bucket of `bucket_span`
+    /// keys that hold one of `num_keys` keys spread uniformly over the bucket
+    fn hit_fraction(num_keys: usize, bucket_span: f64, range_len: f64) -> f64 {
+        1.0 - (1.0 - (range_len / bucket_span).min(1.0)).powi(num_keys as i32)
+    }
 
-        // step 3: compute quotient size or aka implicit bits
-        let bits_used = shared + 1 + redundant_bits; // shared + first_diff + redundant
+    /// share of the empty queries answered true, from the shares of all queries that
+    /// match and that hold a key
+    fn false_positive_share(matching: f64, true_positives: f64) -> f64 {
+        if true_positives >= 1.0 {
+            return 0.0;
+        }
+        ((matching - true_positives) / (1.0 - true_positives)).clamp(0.0, 1.0)
+    }
 
-        if bits_used >= 64 {
-            return (shared, redundant_bits, 0);
+    /// Expected FPR of empty queries for a mix of range lengths
+    ///
+    /// Queries start uniformly between the first and the last sample. The key ranges
+    /// matched by every store are rebuilt from its stored infixes with its own quotient
+    /// and remainder sizes, so shortened infixes and sparse or dense buckets count as
+    /// they are. The true positives among the matching queries are estimated from the
+    /// store's `elem_count`, assuming its keys spread uniformly over the bucket. The
+    /// result is the share of empty queries answered true, comparable to a measured FPR.
+    ///
+    /// # Arguments
+    /// * `range_len_distribution` - (range length, weight) pairs, length 1 is a point query
+    pub fn estimated_fpr(&self, range_len_distribution: &[(K, f64)]) -> f64 {
+        let samples = self.y_fast_trie.keys();
+        let mut weighted_fpr = 0.0;
+        let mut total_weight = 0.0;
+        for &(range_len, weight) in range_len_distribution {
+            let range_len = range_len.max(K::ONE);
+            let (mut false_positives, mut empty_queries) = (0.0, 0.0);
+            for bucket in samples.windows(2) {
+                let (bucket_fp, bucket_empty) =
+                    self.bucket_false_positives(bucket[0], bucket[1], range_len);
+                false_positives += bucket_fp;
+                empty_queries += bucket_empty;
+            }
+            if empty_queries > 0.0 {
+                weighted_fpr += weight * false_positives / empty_queries;
+            }
+            total_weight += weight;
         }
 
-        let remaining_bits = 64 - bits_used;
+        if total_weight > 0.0 {
+            weighted_fpr / total_weight
+        } else {
+            0.0
+        }
+    }
 
-        // try to use BASE_IMPLICIT_SIZE quotient bits
-        if remaining_bits < BASE_IMPLICIT_SIZE as u8 {
-            return (shared, redundant_bits, remaining_bits);
+    /// expected number of false positives and of empty queries among the queries of
+    /// `range_len` keys lying strictly between two consecutive samples
+    fn bucket_false_positives(&self, predecessor: K, successor: K, range_len: K) -> (f64, f64) {
+        // queries holding a sample are true positives
+        let first_start = predecessor + K::ONE;
+        let Some(last_start) = successor.checked_sub(range_len) else {
+            return (0.0, 0.0);
+        };
+        if last_start < first_start {
+            return (0.0, 0.0);
         }
+        let starts = (last_start - first_start).to_f64() + 1.0;
 
-        // extract quotient bits from both keys to check sparsity
-        // let shift = remaining_bits - BASE_IMPLICIT_SIZE as u8;
-        // let quotient_1 = (key_1 >> shift) & ((1u64 << BASE_IMPLICIT_SIZE) - 1);
-        // let quotient_2 = (key_2 >> shift) & ((1u64 << BASE_IMPLICIT_SIZE) - 1);
+        let Some(infix_store) = self.y_fast_trie.get_infix_store(predecessor) else {
+            return (0.0, starts);
+        };
+        let Ok(infix_store) = infix_store.read() else {
+            return (0.0, starts);
+        };
 
-        // TODO: check if there is a better heuristic for the quotient size
-        // add 1 bit if range is sparse (uses < 50% of quotient space)
-        // let range_size = quotient_2 - quotient_1 + 1;
-        // let quotient_bits = if 2 * range_size < (1u64 << BASE_IMPLICIT_SIZE) {
-        //     (BASE_IMPLICIT_SIZE + 1).min(remaining_bits as u32) as u8
-        // } else {
-        //     BASE_IMPLICIT_SIZE as u8
-        // };
+        // starts of queries reaching the key range covered by an infix, and of queries
+        // holding its key, taken at the middle of that range
+        let clip = |lo: K, hi: K| {
+            let lo = lo
+                .checked_sub(range_len - K::ONE)
+                .unwrap_or(K::ZERO)
+                .max(first_start);
+            (lo, hi.min(last_start))
+        };
+        let mut matching = Vec::new();
+        let mut holding_key = Vec::new();
+        for infix in infix_store.to_infixes() {
+            let (lowest, highest) = InfixStore::covered_infixes(infix, self.remainder_size);
+            let key_range = |infix: u64| {
+                Self::infix_key_range(
+                    infix,
+                    predecessor,
+                    successor,
+                    infix_store.quotient_size(),
+                    self.remainder_size,
+                    self.use_redundant_bits,
+                )
+            };
+            let (lowest_key, highest_key) = (key_range(lowest).0, key_range(highest).1);
+            let middle_key = lowest_key + ((highest_key - lowest_key) >> 1);
+            matching.push(clip(lowest_key, highest_key));
+            holding_key.push(clip(middle_key, middle_key));
+        }
 
-        (shared, redundant_bits, BASE_IMPLICIT_SIZE as u8)
+        let matching = Self::union_size(&matching);
+        let true_positives = Self::union_size(&holding_key);
+        (
+            (matching - true_positives).max(0.0),
+            starts - true_positives,
+        )
     }
 
-    /// extract partial key (infix) from a full key
-    /// returns: MSB | quotient_bits | remainder_bits
+    /// number of keys in a union of inclusive key ranges sorted by their lower end
+    /// empty ranges, with lo > hi, are skipped
+    fn union_size(ranges: &[(K, K)]) -> f64 {
+        let mut size = 0.0;
+        let mut merged: Option<(K, K)> = None;
+        for &(lo, hi) in ranges.iter().filter(|(lo, hi)| lo <= hi) {
+            merged = match merged {
+                Some((merged_lo, merged_hi)) if lo <= merged_hi || lo - merged_hi == K::ONE => {
+                    Some((merged_lo, merged_hi.max(hi)))
+                }
+                Some((merged_lo, merged_hi)) => {
+                    size += (merged_hi - merged_lo).to_f64() + 1.0;
+                    Some((lo, hi))
+                }
+                None => Some((lo, hi)),
+            };
+        }
+        if let Some((merged_lo, merged_hi)) = merged {
+            size += (merged_hi - merged_lo).to_f64() + 1.0;
+        }
+        size
+    }
+
+    /// Range emptiness query
+    ///
+    /// Returns false only if no key in `[lo, hi]` was inserted. A true result may be a
+    /// false positive.
     ///
     /// # Arguments
-    /// * `key` - The full key to extract from
-    /// * `shared_prefix_len` - Number of shared prefix bits to skip
-    /// * `redundant_bits` - Number of redundant bits to skip
-    /// * `quotient_bits` - Number of quotient bits to extract (implicit)
-    /// * `remainder_bits` - Number of remainder bits to extract (explicit)
-    /// * `msb` - The first differing bit (0 or 1)
-    fn extract_partial_key(
-        key: Key,
-        shared_prefix_len: u8,
-        redundant_bits: u8,
-        quotient_bits: u8,
-        remainder_bits: u8,
-        msb: u8,
-    ) -> Key {
-        // position where extraction starts (after shared + first_diff + redundant)
-        let start_bit = shared_prefix_len + 1 + redundant_bits;
-
-        if start_bit >= 64 {
-            return msb as Key;
+    /// * `lo` - Lower bound of the range (inclusive)
+    /// * `hi` - Upper bound of the range (inclusive)
+    pub fn may_contain_range(&self, lo: K, hi: K) -> bool {
+        if lo > hi {
+            return false;
         }
 
-        // extract quotient + remainder bits
-        let remaining_bits = 64 - start_bit;
-        let bits_to_extract = (quotient_bits + remainder_bits).min(remaining_bits);
-
-        if bits_to_extract == 0 {
-            return msb as Key;
+        // samples are stored exactly, so a sample inside the range is a true positive.
+        // this also covers ranges spanning several samples
+        let Some(successor) = self.y_fast_trie.successor(lo) else {
+            return false;
+        };
+        if successor <= hi {
+            return true;
         }
 
-        let shift_amount = 64 - start_bit - bits_to_extract;
-        let extracted = (key >> shift_amount) & ((1 << bits_to_extract) - 1);
-
-        // combine: [MSB: 1 bit][quotient: quotient_bits][remainder: remainder_bits]
-        let result = ((msb as Key) << (quotient_bits + remainder_bits)) | extracted;
+        // otherwise the range lies strictly between two consecutive samples
+        let Some(predecessor) = self.y_fast_trie.predecessor(lo) else {
+            return false;
+        };
+        let Some(infix_store) = self.y_fast_trie.get_infix_store(predecessor) else {
+            return false;
+        };
+        let Ok(infix_store) = infix_store.read() else {
+            return false;
+        };
 
-        result
+        let lo_infix = Self::extract_infix(
+            lo,
+            predecessor,
+            successor,
+            infix_store.quotient_size(),
+            self.remainder_size,
+            self.use_redundant_bits,
+        );
+        let hi_infix = Self::extract_infix(
+            hi,
+            predecessor,
+            successor,
+            infix_store.quotient_size(),
+            self.remainder_size,
+            self.use_redundant_bits,
+        );
+        infix_store.any_in_range(lo_infix, hi_infix)
     }
 
-    /// get MSB (first differing bit) between predecessor and successor
-    fn get_msb(key_1: &Key, key_2: &Key) -> u8 {
-        let shared = longest_common_prefix_length(*key_1, *key_2);
-
-        if shared >= 64 {
-            return 0; // keys are identical
+    /// Point membership query
+    ///
+    /// Sampled keys are answered exactly. Any other key is answered from the infix
+    /// store of its predecessor sample, so a true result may be a false positive.
+    pub fn may_contain(&self, key: K) -> bool {
+        // sampled keys are stored exactly in the y-fast trie
+        if self.y_fast_trie.contains(key) {
+            return true;
         }
 
-        // extract bit at position 'shared' (first differing bit)
-        let bit_pos = 63 - shared;
-        ((key_1 >> bit_pos) & 1) as u8
+        let (Some(predecessor), Some(successor)) = (
+            self.y_fast_trie.predecessor(key),
+            self.y_fast_trie.successor(key),
+        ) else {
+            return false;
+        };
+        let Some(infix_store) = self.y_fast_trie.get_infix_store(predecessor) else {
+            return false;
+        };
+        let Ok(infix_store) = infix_store.read() else {
+            return false;
+        };
+
+        let infix = Self::extract_infix(
+            key,
+            predecessor,
+            successor,
+            infix_store.quotient_size(),
+            self.remainder_size,
+            self.use_redundant_bits,
+        );
+        infix_store.contains_infix(infix)
     }
 
-    /// calculate remainder size based on FPR
-    /// FPR ≈ 2 / 2^remainder_size
-    fn choose_remainder_size(_target_size: usize, fpr: f64) -> u8 {
-        // remainder_size = log2(2/FPR) = log2(2) + log2(1/FPR) = 1 - log2(FPR)
-        let remainder_size = (1.0 - fpr.log2()).ceil() as u8;
-        remainder_size.max(4).min(16) // clamp between 4 and 16 bits
+    /// Build a filter over native keys such as `i64`, `f64` or `(u32, u32)`
+    ///
+    /// Keys are stored through their order-preserving `KeyEncoder` encoding, and the
+    /// `_encoded` queries encode their bounds the same way.
+    pub fn new_with_encoded_keys<T: KeyEncoder<Encoded = K>>(
+        keys: &[T],
+        target_size: usize,
+        fpr: f64,
+    ) -> Self {
+        let encoded_keys: Vec<K> = keys.iter().map(|key| key.encode()).collect();
+        Self::new_with_keys(&encoded_keys, target_size, fpr)
     }
 
-    pub fn pretty_print(&self) {
-        print!("{}", self);
+    /// Point membership query on a native key
+    pub fn may_contain_encoded<T: KeyEncoder<Encoded = K>>(&self, key: T) -> bool {
+        self.may_contain(key.encode())
     }
-}
 
-impl fmt::Display for Diva {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(
-            f,
-            "\n╔════════════════════════════════════════════════════════╗"
-        )?;
-        writeln!(
-            f,
-            "║                   DIVA RANGE FILTER                    ║"
-        )?;
-        writeln!(
-            f,
-            "╚════════════════════════════════════════════════════════╝"
-        )?;
+    /// Range emptiness query on native bounds, both inclusive
+    pub fn may_contain_range_encoded<T: KeyEncoder<Encoded = K>>(&self, lo: T, hi: T) -> bool {
+        self.may_contain_range(lo.encode(), hi.encode())
+    }
 
-        // configuration
-        writeln!(f, "\nConfiguration:")?;
-        writeln!(f, "  Target size (T):      {}", self.target_size)?;
-        writeln!(f, "  False positive rate:  {:.4}%", self.fpr * 100.0)?;
-        writeln!(f, "  Remainder size:       {} bits", self.remainder_size)?;
+    /// Insert a native key
+    pub fn insert_encoded<T: KeyEncoder<Encoded = K>>(&mut self, key: T) {
+        self.insert(key.encode());
+    }
 
-        // stats
-        writeln!(f, "\nStatistics:")?;
-        writeln!(f, "  Total keys:           {}", self.y_fast_trie.len())?;
-        writeln!(
-            f,
-            "  Sample count:         {}",
-            self.y_fast_trie.sample_count()
-        )?;
-        let avg_bucket_size = if self.y_fast_trie.sample_count() > 0 {
-            self.y_fast_trie.len() as f64 / self.y_fast_trie.sample_count() as f64
-        } else {
-            0.0
+    /// Insert a key into the filter
+    ///
+    /// Keys outside the sampled range become samples themselves. Any other key is
+    /// stored as an infix in the store of its predecessor sample, and a store growing
+    /// past `target_size` is split in two by promoting a new sample, unless its infixes
+    /// are too close together to split. A full store merges its closest infixes instead,
+    /// the merged keys then share a shortened infix which deletes do not remove.
+    pub fn insert(&mut self, key: K) {
+        if self.y_fast_trie.contains(key) {
+            return;
+        }
+
+        let (Some(predecessor), Some(successor)) = (
+            self.y_fast_trie.predecessor(key),
+            self.y_fast_trie.successor(key),
+        ) else {
+            // nothing brackets the key, so it becomes the new first or last sample
+            self.y_fast_trie.insert(key);
+            return;
         };
-        writeln!(f, "  Avg keys per bucket:  {:.1}", avg_bucket_size)?;
 
-        // underlying Y-Fast Trie structure
-        write!(f, "{}", self.y_fast_trie)?;
+        let elem_count = match self.y_fast_trie.get_infix_store(predecessor) {
+            Some(infix_store) => {
+                let Ok(mut infix_store) = infix_store.write() else {
+                    return;
+                };
+                let infix = Self::extract_infix(
+                    key,
+                    predecessor,
+                    successor,
+                    infix_store.quotient_size(),
+                    self.remainder_size,
+                    self.use_redundant_bits,
+                );
+                match infix_store.insert_resizing(infix) {
+                    Ok(()) => {
+                        Self::debug_validate(&infix_store);
+                        infix_store.elem_count()
+                    }
+                    Err(InfixStoreError::Full { .. }) => {
+                        drop(infix_store);
+                        self.insert_into_full_bucket(predecessor, successor, infix);
+                        return;
+                    }
+                    Err(error) => unreachable!(
+                        "infixes are extracted with the quotient size of their store: {}",
+                        error
+                    ),
+                }
+            }
+            None => {
+                let quotient_size = self.updated_quotient_size();
+                let infix = Self::extract_infix(
+                    key,
+                    predecessor,
+                    successor,
+                    quotient_size,
+                    self.remainder_size,
+                    self.use_redundant_bits,
+                );
+                let infix_store = InfixStore::new_with_quotient_size(
+                    &[infix],
+                    quotient_size,
+                    self.remainder_size,
+                );
+                self.y_fast_trie.set_infix_store(predecessor, infix_store);
+                1
+            }
+        };
 
-        Ok(())
+        if elem_count > self.target_size {
+            self.split_bucket(predecessor, successor);
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// store an infix in the bucket between two samples whose store is at the largest size grade
+    ///
+    /// Happens with a target size of MAX_ELEMENTS, which the largest size grade reaches
+    /// before the bucket is due to split, or when no split could shrink the bucket. The
+    /// bucket is split with the new infix in it, or if no split shrinks it either, its
+    /// closest infixes are merged. Either way no store goes past MAX_ELEMENTS.
+    fn insert_into_full_bucket(&mut self, predecessor: K, successor: K, infix: u64) {
+        let mut infixes = self.bucket_infixes(predecessor);
+        let quotient_size = self.bucket_quotient_size(predecessor);
+        infixes.push(infix);
+        Self::sort_infixes(&mut infixes);
+        if self.split_infixes(predecessor, successor, &infixes, quotient_size) {
+            return;
+        }
 
-    #[test]
-    fn test_choose_remainder_size() {
-        // FPR = 1% -> remainder_size = 8
-        assert_eq!(Diva::choose_remainder_size(1024, 0.01), 8);
-        // FPR = 0.1% -> remainder_size = 11
-        assert_eq!(Diva::choose_remainder_size(1024, 0.001), 11);
-        assert_eq!(Diva::choose_remainder_size(1024, 0.1), 5);
+        // the infixes are too close together for any sample to split them. more infixes
+        // than quotients always leave two sharing one, so coarser quotients end the loop
+        let mut quotient_size = quotient_size;
+        while !Self::merge_closest_infixes(&mut infixes, self.remainder_size) {
+            assert!(
+                quotient_size > MIN_QUOTIENT_SIZE,
+                "{} infixes with {} quotients leave none to merge",
+                infixes.len(),
+                1 << quotient_size
+            );
+            infixes = Self::reextract_infixes(
+                &infixes,
+                (predecessor, successor),
+                (predecessor, successor),
+                (quotient_size, quotient_size - 1),
+                self.remainder_size,
+                self.use_redundant_bits,
+            );
+            Self::sort_infixes(&mut infixes);
+            quotient_size -= 1;
+        }
+        self.set_bucket_infixes(predecessor, infixes, quotient_size);
     }
 
-    #[test]
-    fn test_get_msb() {
-        // first differing bit is 0
-        let key1 = 0b0000_0000_0000_0000u64;
-        let key2 = 0b1111_1111_1111_1111u64;
-        assert_eq!(Diva::get_msb(&key1, &key2), 0);
+    /// replace the closest two infixes sharing a quotient with the shortened infix covering
+    /// both, and drop the infixes it covers. returns false if no two infixes share a quotient
+    fn merge_closest_infixes(infixes: &mut Vec<u64>, remainder_size: u8) -> bool {
+        let merged = infixes
+            .windows(2)
+            .filter_map(|pair| {
+                let (lowest, _) = InfixStore::covered_infixes(pair[0], remainder_size);
+                let (_, highest) = InfixStore::covered_infixes(pair[1], remainder_size);
+                // shortened so deletes cannot remove it, and within one quotient
+                let terminator = (1..remainder_size).find(|&terminator| {
+                    lowest >> (terminator + 1) == highest >> (terminator + 1)
+                })?;
+                Some(((lowest >> (terminator + 1)) << (terminator + 1)) | (1 << terminator))
+            })
+            .min_by_key(|merged| merged.trailing_zeros());
+        let Some(merged) = merged else {
+            return false;
+        };
 
-        // first differing bit is 1
-        let key1 = 0b1000_0000_0000_0000u64 << 48;
-        let key2 = 0b0111_1111_1111_1111u64 << 48;
-        assert_eq!(Diva::get_msb(&key1, &key2), 1);
+        let (lowest, highest) = InfixStore::covered_infixes(merged, remainder_size);
+        infixes.retain(|&infix| {
+            let (infix_lowest, infix_highest) = InfixStore::covered_infixes(infix, remainder_size);
+            infix_highest < lowest || infix_lowest > highest
+        });
+        infixes.push(merged);
+        Self::sort_infixes(infixes);
+        true
     }
 
-    #[test]
-    fn test_extraction_params() {
-        let key1 = 0b0000_0000_1111_0000u64 << 48;
-        let key2 = 0b0000_0000_1111_1111u64 << 48;
-
-        let (shared, _redundant, quotient) =
-            Diva::get_shared_ignore_implicit_size(&key1, &key2, false);
+    /// check the invariants of a store updated in place, in debug builds only
+    fn debug_validate(infix_store: &InfixStore) {
+        if cfg!(debug_assertions)
+            && let Err(error) = infix_store.validate()
+        {
+            panic!("corrupted infix store after an update: {}", error);
+        }
+    }
 
-        assert_eq!(shared, 12); // 12 bits shared prefix
-        // assert_eq!(redundant, 0);
-        assert!(quotient >= 10); // at least 10 bits for quotient
+    /// quotient size of buckets rebuilt by updates
+    /// such buckets keep taking keys until they split, so they are sized for a full bucket
+    fn updated_quotient_size(&self) -> u8 {
+        InfixStore::choose_quotient_size(self.target_size)
     }
 
-    #[test]
-    fn test_construction_small_dataset() {
-        // 100 keys, all fit in one sample
-        let keys: Vec<u64> = (0..100).map(|i| i * 1000).collect();
-        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+    /// Delete a key from the filter
+    ///
+    /// Returns false to report a key that was never inserted or is deleted already:
+    /// no stored fingerprint matches it. A key matching only a fingerprint shortened by
+    /// an earlier split and shared with other keys is reported the same way, and the
+    /// fingerprint is kept.
+    ///
+    /// A fingerprint cannot tell apart keys it is shared by, so a never-inserted key
+    /// whose fingerprint equals an inserted key's is not reported: that key's
+    /// fingerprint is removed and true is returned. Only delete keys that were inserted.
+    pub fn delete(&mut self, key: K) -> bool {
+        if self.y_fast_trie.contains(key) {
+            self.delete_sample(key);
+            return true;
+        }
 
-        assert_eq!(diva.target_size, 1024);
-        assert_eq!(diva.fpr, 0.01);
-        assert_eq!(diva.remainder_size, 8);
+        let (Some(predecessor), Some(successor)) = (
+            self.y_fast_trie.predecessor(key),
+            self.y_fast_trie.successor(key),
+        ) else {
+            return false;
+        };
+        let Some(infix_store) = self.y_fast_trie.get_infix_store(predecessor) else {
+            return false;
+        };
+        let Ok(mut infix_store) = infix_store.write() else {
+            return false;
+        };
+
+        let infix = Self::extract_infix(
+            key,
+            predecessor,
+            successor,
+            infix_store.quotient_size(),
+            self.remainder_size,
+            self.use_redundant_bits,
+        );
+        let removed = infix_store.remove_resizing(infix).is_ok();
+        Self::debug_validate(&infix_store);
+        removed
     }
 
-    #[test]
-    fn test_construction_with_sampling() {
-        // 5000 keys - should create ~5 samples
-        let keys: Vec<u64> = (0..5000).map(|i| i as u64).collect();
-        let target_size = 1024;
-        let diva = Diva::new_with_keys(&keys, target_size, 0.01);
+    /// remove a sample and merge the buckets on either side of it
+    fn delete_sample(&mut self, sample: K) {
+        let remainder_size = self.remainder_size;
+        let use_redundant_bits = self.use_redundant_bits;
+        let predecessor = sample
+            .checked_sub(K::ONE)
+            .and_then(|key| self.y_fast_trie.predecessor(key));
+        let successor = sample
+            .checked_add(K::ONE)
+            .and_then(|key| self.y_fast_trie.successor(key));
+        let lower_infixes = predecessor
+            .map(|predecessor| self.bucket_infixes(predecessor))
+            .unwrap_or_default();
+        let upper_infixes = self.bucket_infixes(sample);
+        let quotient_size = self.updated_quotient_size();
+        let lower_quotient_size = predecessor
+            .map(|predecessor| self.bucket_quotient_size(predecessor))
+            .unwrap_or(quotient_size);
+        let upper_quotient_size = self.bucket_quotient_size(sample);
 
-        // +1 because we sample the last key too
-        let expected_samples = (keys.len() + target_size - 1) / target_size + 1;
-        let actual_samples = diva.y_fast_trie.len();
+        self.y_fast_trie.remove(sample);
 
-        assert_eq!(actual_samples, expected_samples);
-    }
+        match (predecessor, successor) {
+            (Some(predecessor), Some(successor)) => {
+                let mut infixes = Self::reextract_infixes(
+                    &lower_infixes,
+                    (predecessor, sample),
+                    (predecessor, successor),
+                    (lower_quotient_size, quotient_size),
+                    remainder_size,
+                    use_redundant_bits,
+                );
+                infixes.extend(Self::reextract_infixes(
+                    &upper_infixes,
+                    (sample, successor),
+                    (predecessor, successor),
+                    (upper_quotient_size, quotient_size),
+                    remainder_size,
+                    use_redundant_bits,
+                ));
+                Self::sort_infixes(&mut infixes);
 
-    #[test]
-    fn test_construction_single_sample() {
-        // 500 keys < target_size -> only 1 sample
-        let keys: Vec<u64> = (0..500).map(|i| i * 10).collect();
-        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+                if infixes.len() > self.target_size
+                    && self.split_infixes(predecessor, successor, &infixes, quotient_size)
+                {
+                    return;
+                }
+                if infixes.len() <= MAX_ELEMENTS {
+                    self.set_bucket_infixes(predecessor, infixes, quotient_size);
+                    return;
+                }
 
-        assert_eq!(diva.y_fast_trie.sample_count(), 1);
+                // no single store holds the merged buckets, so the sample stays
+                self.y_fast_trie.insert(sample);
+                self.set_bucket_infixes(predecessor, lower_infixes, lower_quotient_size);
+                self.set_bucket_infixes(sample, upper_infixes, upper_quotient_size);
+            }
+            (Some(predecessor), None) => {
+                // the last sample is gone, so the highest key of the lower bucket replaces it
+                let Some(&last_infix) = lower_infixes.last() else {
+                    return;
+                };
+                let (_, highest) = InfixStore::covered_infixes(last_infix, remainder_size);
+                let (_, new_sample) = Self::infix_key_range(
+                    highest,
+                    predecessor,
+                    sample,
+                    lower_quotient_size,
+                    remainder_size,
+                    use_redundant_bits,
+                );
+                let new_sample = new_sample.min(sample - K::ONE);
+
+                self.y_fast_trie.insert(new_sample);
+                let infixes = Self::reextract_infixes(
+                    &lower_infixes,
+                    (predecessor, sample),
+                    (predecessor, new_sample),
+                    (lower_quotient_size, quotient_size),
+                    remainder_size,
+                    use_redundant_bits,
+                );
+                self.set_bucket_infixes(predecessor, infixes, quotient_size);
+            }
+            (None, Some(successor)) => {
+                // the first sample is gone, so the lowest key of the upper bucket replaces it
+                let Some(&first_infix) = upper_infixes.first() else {
+                    return;
+                };
+                let (lowest, _) = InfixStore::covered_infixes(first_infix, remainder_size);
+                let (new_sample, _) = Self::infix_key_range(
+                    lowest,
+                    sample,
+                    successor,
+                    upper_quotient_size,
+                    remainder_size,
+                    use_redundant_bits,
+                );
+                let new_sample = new_sample.max(sample + K::ONE);
+
+                self.y_fast_trie.insert(new_sample);
+                let infixes = Self::reextract_infixes(
+                    &upper_infixes,
+                    (sample, successor),
+                    (new_sample, successor),
+                    (upper_quotient_size, quotient_size),
+                    remainder_size,
+                    use_redundant_bits,
+                );
+                self.set_bucket_infixes(new_sample, infixes, quotient_size);
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// infixes stored in the bucket that starts at a sample
+    fn bucket_infixes(&self, sample: K) -> Vec<u64> {
+        self.y_fast_trie
+            .get_infix_store(sample)
+            .and_then(|infix_store| {
+                infix_store
+                    .read()
+                    .ok()
+                    .map(|infix_store| infix_store.to_infixes())
+            })
+            .unwrap_or_default()
+    }
+
+    /// quotient size of the infix store of the bucket that starts at a sample
+    fn bucket_quotient_size(&self, sample: K) -> u8 {
+        self.y_fast_trie
+            .get_infix_store(sample)
+            .and_then(|infix_store| {
+                infix_store
+                    .read()
+                    .ok()
+                    .map(|infix_store| infix_store.quotient_size())
+            })
+            .unwrap_or_else(|| self.updated_quotient_size())
+    }
+
+    /// split the bucket between two consecutive samples, see `split_infixes`
+    fn split_bucket(&mut self, predecessor: K, successor: K) -> bool {
+        let infixes = self.bucket_infixes(predecessor);
+        let quotient_size = self.bucket_quotient_size(predecessor);
+        self.split_infixes(predecessor, successor, &infixes, quotient_size)
+    }
+
+    /// split sorted infixes of the bucket between two consecutive samples in two buckets
+    /// by promoting a new sample, then re-extract every infix against its new boundaries
+    ///
+    /// The new sample is the lowest key of a stored infix that starts after every smaller
+    /// infix ends, the one closest to the median. Re-extracting against a narrower bucket
+    /// can spread a wide infix over several quotients, so a split is only made if both
+    /// halves hold fewer infixes than `infixes`. Returns whether the bucket was split.
+    ///
+    /// # Arguments
+    /// * `predecessor` - Sample the bucket starts at
+    /// * `successor` - Sample the bucket ends at
+    /// * `infixes` - Sorted infixes of the bucket
+    /// * `quotient_size` - Quotient size the infixes were extracted with
+    fn split_infixes(
+        &mut self,
+        predecessor: K,
+        successor: K,
+        infixes: &[u64],
+        quotient_size: u8,
+    ) -> bool {
+        let remainder_size = self.remainder_size;
+        let use_redundant_bits = self.use_redundant_bits;
+
+        // keys each infix stands for, shortened infixes stand for more than one
+        let key_range = |infix| {
+            Self::infix_key_range(
+                infix,
+                predecessor,
+                successor,
+                quotient_size,
+                remainder_size,
+                use_redundant_bits,
+            )
+        };
+        let key_ranges: Vec<(K, K)> = infixes
+            .iter()
+            .map(|&infix| {
+                let (lowest, highest) = InfixStore::covered_infixes(infix, remainder_size);
+                (key_range(lowest).0, key_range(highest).1)
+            })
+            .collect();
+
+        // an infix starting after every smaller one ends puts no infix on both sides
+        let mut boundaries = Vec::new();
+        let mut highest_below = predecessor;
+        for (index, &(lowest_key, highest_key)) in key_ranges.iter().enumerate() {
+            if index > 0 && lowest_key > highest_below && lowest_key < successor {
+                boundaries.push(index);
+            }
+            highest_below = highest_below.max(highest_key);
+        }
+        boundaries.sort_by_key(|&index| index.abs_diff(infixes.len() / 2));
+
+        boundaries.into_iter().any(|index| {
+            self.split_infixes_at(
+                predecessor,
+                successor,
+                infixes,
+                quotient_size,
+                key_ranges[index].0,
+            )
+        })
+    }
+
+    /// split sorted infixes of the bucket between two consecutive samples at a new sample
+    /// if both halves hold fewer infixes, see `split_infixes`. returns whether it split
+    fn split_infixes_at(
+        &mut self,
+        predecessor: K,
+        successor: K,
+        infixes: &[u64],
+        quotient_size: u8,
+        sample: K,
+    ) -> bool {
+        let split_quotient_size = self.updated_quotient_size();
+        let [lower_infixes, upper_infixes] =
+            [(predecessor, sample), (sample, successor)].map(|bucket| {
+                let mut half = Self::reextract_infixes(
+                    infixes,
+                    (predecessor, successor),
+                    bucket,
+                    (quotient_size, split_quotient_size),
+                    self.remainder_size,
+                    self.use_redundant_bits,
+                );
+                Self::sort_infixes(&mut half);
+                half
+            });
+        if lower_infixes.len() >= infixes.len() || upper_infixes.len() >= infixes.len() {
+            return false;
+        }
+
+        self.y_fast_trie.insert(sample);
+        self.set_bucket_infixes(predecessor, lower_infixes, split_quotient_size);
+        self.set_bucket_infixes(sample, upper_infixes, split_quotient_size);
+        true
+    }
+
+    /// replace the infix store of the bucket that starts at a sample
+    fn set_bucket_infixes(&mut self, sample: K, mut infixes: Vec<u64>, quotient_size: u8) {
+        Self::sort_infixes(&mut infixes);
+        self.y_fast_trie.set_infix_store(
+            sample,
+            InfixStore::new_with_quotient_size(&infixes, quotient_size, self.remainder_size),
+        );
+    }
+
+    /// sort infixes and drop repeated shortened ones
+    fn sort_infixes(infixes: &mut Vec<u64>) {
+        infixes.sort();
+        // shortened infixes may repeat, full-length ones each stand for a distinct key
+        infixes.dedup_by(|a, b| a == b && *a & 1 == 0);
+    }
+
+    /// re-extract the infixes of one bucket against the samples of another
+    ///
+    /// # Arguments
+    /// * `infixes` - Infixes extracted between the `from` samples
+    /// * `from` - (predecessor, successor) samples the infixes were extracted against
+    /// * `to` - (predecessor, successor) samples of the new bucket
+    /// * `quotient_sizes` - (from, to) quotient sizes of the two buckets
+    /// * `remainder_size` - Number of remainder bits
+    /// * `use_redundant_bits` - Whether redundant bits are skipped, see `compute_redundant_bits`
+    fn reextract_infixes(
+        infixes: &[u64],
+        from: (K, K),
+        to: (K, K),
+        quotient_sizes: (u8, u8),
+        remainder_size: u8,
+        use_redundant_bits: bool,
+    ) -> Vec<u64> {
+        let (from_quotient_size, to_quotient_size) = quotient_sizes;
+        let mut reextracted = Vec::new();
+        for &infix in infixes {
+            // only the key range behind each infix is known, not the original key
+            let (lowest, highest) = InfixStore::covered_infixes(infix, remainder_size);
+            let (lowest_key, _) = Self::infix_key_range(
+                lowest,
+                from.0,
+                from.1,
+                from_quotient_size,
+                remainder_size,
+                use_redundant_bits,
+            );
+            let (_, highest_key) = Self::infix_key_range(
+                highest,
+                from.0,
+                from.1,
+                from_quotient_size,
+                remainder_size,
+                use_redundant_bits,
+            );
+
+            reextracted.extend(Self::reextract_key_range(
+                lowest_key,
+                highest_key,
+                lowest == highest,
+                to,
+                to_quotient_size,
+                remainder_size,
+                use_redundant_bits,
+            ));
+        }
+        reextracted
+    }
+
+    /// infixes covering the part of a key range that lies between two samples
+    ///
+    /// # Arguments
+    /// * `lowest_key` - Lower bound of the key range (inclusive)
+    /// * `highest_key` - Upper bound of the key range (inclusive)
+    /// * `exact` - Whether the range stands for a single key's full-length infix
+    /// * `bucket` - (predecessor, successor) samples of the new bucket
+    /// * `quotient_size` - Number of quotient bits of the new bucket
+    /// * `remainder_size` - Number of remainder bits
+    /// * `use_redundant_bits` - Whether redundant bits are skipped, see `compute_redundant_bits`
+    fn reextract_key_range(
+        lowest_key: K,
+        highest_key: K,
+        exact: bool,
+        bucket: (K, K),
+        quotient_size: u8,
+        remainder_size: u8,
+        use_redundant_bits: bool,
+    ) -> Vec<u64> {
+        let (predecessor, successor) = bucket;
+        let lowest_key = lowest_key.max(predecessor + K::ONE);
+        let highest_key = highest_key.min(successor - K::ONE);
+        if lowest_key > highest_key {
+            return Vec::new();
+        }
+
+        let first = Self::extract_infix(
+            lowest_key,
+            predecessor,
+            successor,
+            quotient_size,
+            remainder_size,
+            use_redundant_bits,
+        );
+        let last = Self::extract_infix(
+            highest_key,
+            predecessor,
+            successor,
+            quotient_size,
+            remainder_size,
+            use_redundant_bits,
+        );
+
+        // spanning several quotients: every one of them is covered as a whole
+        if first >> remainder_size != last >> remainder_size {
+            return ((first >> remainder_size)..=(last >> remainder_size))
+                .map(|quotient| (quotient << remainder_size) | (1 << (remainder_size - 1)))
+                .collect();
+        }
+
+        // smallest shortened infix covering both ends. a range that was not a single
+        // key's infix is never stored at full length, so deletes cannot remove it
+        let mut terminator = if exact { 0 } else { 1 };
+        while first >> (terminator + 1) != last >> (terminator + 1) {
+            terminator += 1;
+        }
+        vec![((first >> (terminator + 1)) << (terminator + 1)) | (1 << terminator)]
+    }
+
+    /// range of keys between two samples whose full-length infix is `infix`
+    /// returns: (lowest_key, highest_key) inclusive, the inverse of extract_partial_key
+    fn infix_key_range(
+        infix: u64,
+        predecessor: K,
+        successor: K,
+        quotient_size: u8,
+        remainder_size: u8,
+        use_redundant_bits: bool,
+    ) -> (K, K) {
+        let (shared_prefix_len, redundant_bits, quotient_bits) =
+            Self::get_shared_ignore_implicit_size(
+                &predecessor,
+                &successor,
+                use_redundant_bits,
+                quotient_size,
+            );
+        let payload_bits = (quotient_bits + remainder_size).saturating_sub(2) as u32;
+        let msb = (infix >> (payload_bits + 1)) & 1;
+        let payload = (infix >> 1) & ((1 << payload_bits) - 1);
+
+        // shared prefix, then the MSB, then redundant bits which are always the MSB flipped
+        let shared_prefix_len = shared_prefix_len as u32;
+        let redundant_bits = redundant_bits as u32;
+        let start_bit = shared_prefix_len + 1 + redundant_bits;
+        let tail_len = (K::BITS as u32).saturating_sub(start_bit);
+
+        let prefix = predecessor & !Self::low_bits_mask(K::BITS as u32 - shared_prefix_len);
+        let msb_bit = K::from_u64(msb) << (K::BITS - 1 - shared_prefix_len as usize);
+        let redundant = if msb == 0 {
+            Self::low_bits_mask(redundant_bits) << tail_len as usize
+        } else {
+            K::ZERO
+        };
+        let key = prefix | msb_bit | redundant;
+
+        // undo the left alignment of the tail
+        if tail_len >= payload_bits {
+            let free_bits = tail_len - payload_bits;
+            let lowest = key | (K::from_u64(payload) << free_bits as usize);
+            (lowest, lowest | Self::low_bits_mask(free_bits))
+        } else {
+            let tail = K::from_u64(payload >> (payload_bits - tail_len));
+            (key | tail, key | tail)
+        }
+    }
+
+    /// extract the infix of a key lying between two consecutive samples
+    /// used by construction and by every query, so both always agree on the infix layout
+    fn extract_infix(
+        key: K,
+        predecessor: K,
+        successor: K,
+        quotient_size: u8,
+        remainder_size: u8,
+        use_redundant_bits: bool,
+    ) -> u64 {
+        let (shared_prefix_len, redundant_bits, quotient_bits) =
+            Self::get_shared_ignore_implicit_size(
+                &predecessor,
+                &successor,
+                use_redundant_bits,
+                quotient_size,
+            );
+        Self::extract_partial_key(
+            key,
+            shared_prefix_len,
+            redundant_bits,
+            quotient_bits,
+            remainder_size,
+            Self::bit_after_prefix(key, shared_prefix_len),
+        )
+    }
+
+    /// compute redundant bits after first differing bit
+    /// a key in [pred, succ] whose MSB is 0 is at least pred, so it repeats every 1 bit
+    /// pred has right after the MSB. likewise a key whose MSB is 1 repeats the 0 bits
+    /// succ has there. where pred has 1 and succ has 0 the bit is always the MSB
+    /// flipped, so it can be skipped and rebuilt from the MSB
+    fn compute_redundant_bits(key_1: K, key_2: K, shared_prefix_len: u8) -> u8 {
+        if shared_prefix_len as usize >= K::BITS - 1 {
+            return 0;
+        }
+
+        let mut redundant_bits = 0u8;
+
+        // start after shared prefix + 1 (skip first differing bit)
+        let start_pos = shared_prefix_len + 1;
+
+        for bit_pos in start_pos as usize..K::BITS {
+            let shift = K::BITS - 1 - bit_pos;
+            let bit_1 = (key_1 >> shift) & K::ONE;
+            let bit_2 = (key_2 >> shift) & K::ONE;
+
+            // redundant if pred has 1 and succ has 0 (opposite of first diff bit)
+            if bit_1 == K::ONE && bit_2 == K::ZERO {
+                redundant_bits += 1;
+            } else {
+                break; // stop at first non-redundant bit
+            }
+        }
+
+        redundant_bits
+    }
+
+    /// compute shared prefix, redundant bits, and quotient size
+    /// returns: (shared_prefix_len, redundant_bits, quotient_bits)
+    ///
+    /// # Arguments
+    /// * `quotient_size` - Quotient size of the bucket's infix store, capped by the key bits left
+    fn get_shared_ignore_implicit_size(
+        key_1: &K,
+        key_2: &K,
+        use_redundant_bits: bool,
+        quotient_size: u8,
+    ) -> (u8, u8, u8) {
+        // step 1: find shared prefix length (LCP)
+        let shared = longest_common_prefix_length(*key_1, *key_2) as u8;
+
+        // step 2: compute redundant bits
+        let redundant_bits = if use_redundant_bits {
+            Self::compute_redundant_bits(*key_1, *key_2, shared)
+        } else {
+            0
+        };
+
+        // step 3: compute quotient size or aka implicit bits
+        // the first differing bit (MSB) is the top quotient bit, redundant bits are skipped
+        let bits_skipped = shared + redundant_bits;
+
+        if bits_skipped as usize >= K::BITS {
+            return (shared, redundant_bits, 0);
+        }
+
+        let remaining_bits = K::BITS as u8 - bits_skipped;
+        (shared, redundant_bits, quotient_size.min(remaining_bits))
+    }
+
+    /// extract partial key (infix) from a full key
+    /// returns: quotient_bits | remainder_bits, where the quotient starts with the MSB
+    ///
+    /// The key bits following the MSB and the redundant bits fill the infix from the top,
+    /// and the lowest bit is always set. That bit terminates the infix: an infix whose
+    /// lowest set bit sits higher stands for every infix sharing the bits above it.
+    ///
+    /// # Arguments
+    /// * `key` - The full key to extract from
+    /// * `shared_prefix_len` - Number of shared prefix bits to skip
+    /// * `redundant_bits` - Number of redundant bits to skip
+    /// * `quotient_bits` - Number of quotient bits to extract, MSB included (implicit)
+    /// * `remainder_bits` - Number of remainder bits to extract (explicit)
+    /// * `msb` - The key's bit at the first position where the boundary samples differ
+    fn extract_partial_key(
+        key: K,
+        shared_prefix_len: u8,
+        redundant_bits: u8,
+        quotient_bits: u8,
+        remainder_bits: u8,
+        msb: u8,
+    ) -> u64 {
+        // key bits stored after the MSB, the lowest bit is kept for the terminator
+        let payload_bits = (quotient_bits + remainder_bits).saturating_sub(2) as u32;
+
+        // position where extraction starts (after shared + first_diff + redundant)
+        let start_bit = (shared_prefix_len + 1 + redundant_bits) as u32;
+        let tail_len = (K::BITS as u32).saturating_sub(start_bit);
+        let tail = key & Self::low_bits_mask(tail_len);
+
+        // left-align the tail, padding with zeros once the key runs out of bits
+        let payload = if tail_len >= payload_bits {
+            (tail >> (tail_len - payload_bits) as usize).low_u64()
+        } else {
+            tail.low_u64() << (payload_bits - tail_len)
+        };
+
+        // combine: [MSB: 1 bit][payload: payload_bits][terminator: 1 bit]
+        ((((msb as u64) << payload_bits) | payload) << 1) | 1
+    }
+
+    /// get the key's bit right after the shared prefix of its boundary samples
+    fn bit_after_prefix(key: K, shared_prefix_len: u8) -> u8 {
+        if shared_prefix_len as usize >= K::BITS {
+            return 0;
+        }
+        ((key >> (K::BITS - 1 - shared_prefix_len as usize)) & K::ONE).low_u64() as u8
+    }
+
+    /// mask of the lowest `bits` bits of a key
+    fn low_bits_mask(bits: u32) -> K {
+        if bits == 0 {
+            K::ZERO
+        } else {
+            K::MAX >> (K::BITS - bits as usize)
+        }
+    }
+
+    /// size of the filter in bits: the sample keys and the infix store data words
+    pub fn size_in_bits(&self) -> usize {
+        let samples = self.y_fast_trie.keys();
+        let infix_store_words: usize = samples
+            .iter()
+            .filter_map(|&sample| self.y_fast_trie.get_infix_store(sample))
+            .filter_map(|infix_store| infix_store.read().ok().map(|store| store.data().len()))
+            .sum();
+        samples.len() * K::BITS + infix_store_words * U64_BITS
+    }
+
+    /// Estimated memory used by the filter, split between its parts
+    ///
+    /// `key_count` counts the samples plus the infixes stored between them, so
+    /// `bits_per_key` is the cost of every key the filter was built from.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = self.y_fast_trie.memory_usage();
+        usage.filter_fields = size_of::<Self>() - size_of::<YFastTrie<K>>();
+        usage.key_count += self
+            .y_fast_trie
+            .keys()
+            .into_iter()
+            .filter_map(|sample| self.y_fast_trie.get_infix_store(sample))
+            .filter_map(|infix_store| infix_store.read().ok().map(|store| store.elem_count()))
+            .sum::<usize>();
+        usage
+    }
+
+    /// Configuration, bucket and memory summary of the filter
+    pub fn stats(&self) -> DivaStats {
+        let samples = self.y_fast_trie.keys();
+        let mut stats = DivaStats {
+            config: DivaConfig {
+                target_size: self.target_size,
+                fpr: self.fpr,
+                use_redundant_bits: self.use_redundant_bits,
+                remainder_size: Some(self.remainder_size),
+            },
+            sample_count: samples.len(),
+            infix_store_count: 0,
+            infix_count: 0,
+            max_bucket_size: 0,
+            slot_count: 0,
+            quotient_sizes: Vec::new(),
+            size_in_bits: self.size_in_bits(),
+            memory: self.memory_usage(),
+        };
+
+        let mut quotient_sizes = BTreeMap::new();
+        for sample in samples {
+            let Some(infix_store) = self.y_fast_trie.get_infix_store(sample) else {
+                continue;
+            };
+            let Ok(infix_store) = infix_store.read() else {
+                continue;
+            };
+            stats.infix_store_count += 1;
+            stats.infix_count += infix_store.elem_count();
+            stats.max_bucket_size = stats.max_bucket_size.max(infix_store.elem_count());
+            stats.slot_count += infix_store.num_slots();
+            *quotient_sizes
+                .entry(infix_store.quotient_size())
+                .or_insert(0) += 1;
+        }
+        stats.quotient_sizes = quotient_sizes.into_iter().collect();
+        stats
+    }
+
+    /// Check the invariants of every infix store, see `InfixStore::validate`
+    ///
+    /// Debug builds run it after construction and check each store updated in place.
+    /// returns the error of the first invalid store
+    pub fn validate_infix_stores(&self) -> Result<(), InfixStoreError> {
+        for sample in self.y_fast_trie.keys() {
+            if let Some(infix_store) = self.y_fast_trie.get_infix_store(sample)
+                && let Ok(infix_store) = infix_store.read()
+            {
+                infix_store.validate()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize the filter into a writer
+    ///
+    /// All integers are little-endian:
+    /// [magic: "DIVA"] [version: u16] [key_bits: u8] [target_size: u64] [fpr: f64]
+    /// [remainder_size: u8] [use_redundant_bits: u8] [sample_count: u64], then for each
+    /// sample in order [key: key_bits / 8 bytes] [has_infix_store: u8] and, if it has an infix store,
+    /// [elem_count: u16] [size_grade: u8] [quotient_size: u8] [word_count: u64]
+    /// [data: word_count * u64]
+    pub fn serialize_into(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&SERIALIZATION_MAGIC)?;
+        writer.write_all(&SERIALIZATION_VERSION.to_le_bytes())?;
+        writer.write_all(&[K::BITS as u8])?;
+        writer.write_all(&(self.target_size as u64).to_le_bytes())?;
+        writer.write_all(&self.fpr.to_le_bytes())?;
+        writer.write_all(&[self.remainder_size])?;
+        writer.write_all(&[self.use_redundant_bits as u8])?;
+
+        let samples = self.y_fast_trie.keys();
+        writer.write_all(&(samples.len() as u64).to_le_bytes())?;
+        for sample in samples {
+            sample.write_le(writer)?;
+            let Some(infix_store) = self.y_fast_trie.get_infix_store(sample) else {
+                writer.write_all(&[0])?;
+                continue;
+            };
+            let infix_store = infix_store
+                .read()
+                .map_err(|_| io::Error::other("infix store lock poisoned"))?;
+
+            writer.write_all(&[1])?;
+            writer.write_all(&(infix_store.elem_count() as u16).to_le_bytes())?;
+            writer.write_all(&[infix_store.size_grade()])?;
+            writer.write_all(&[infix_store.quotient_size()])?;
+            writer.write_all(&(infix_store.data().len() as u64).to_le_bytes())?;
+            for word in infix_store.data() {
+                writer.write_all(&word.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deserialize a filter written by `serialize_into`
+    ///
+    /// Truncated input fails with `UnexpectedEof`, anything that does not describe
+    /// a valid filter fails with `InvalidData`.
+    pub fn deserialize_from(reader: &mut impl Read) -> io::Result<Self> {
+        if Self::read_bytes::<4>(reader)? != SERIALIZATION_MAGIC {
+            return Err(Self::invalid_data("not a serialized Diva".to_string()));
+        }
+        let version = u16::from_le_bytes(Self::read_bytes(reader)?);
+        if version != SERIALIZATION_VERSION {
+            return Err(Self::invalid_data(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let [key_bits] = Self::read_bytes(reader)?;
+        if key_bits as usize != K::BITS {
+            return Err(Self::invalid_data(format!(
+                "filter has {} bit keys, expected {}",
+                key_bits,
+                K::BITS
+            )));
+        }
+
+        let target_size = usize::try_from(u64::from_le_bytes(Self::read_bytes(reader)?))
+            .ok()
+            .filter(|target_size| (1..=MAX_ELEMENTS).contains(target_size))
+            .ok_or_else(|| Self::invalid_data("invalid target size".to_string()))?;
+        let fpr = f64::from_le_bytes(Self::read_bytes(reader)?);
+        if !(fpr > 0.0 && fpr < 1.0) {
+            return Err(Self::invalid_data(format!("invalid fpr {}", fpr)));
+        }
+        let [remainder_size] = Self::read_bytes(reader)?;
+        if !(MIN_REMAINDER_SIZE..=MAX_REMAINDER_SIZE).contains(&remainder_size) {
+            return Err(Self::invalid_data(format!(
+                "invalid remainder size {}",
+                remainder_size
+            )));
+        }
+        let use_redundant_bits = match Self::read_bytes(reader)? {
+            [0] => false,
+            [1] => true,
+            [flag] => {
+                return Err(Self::invalid_data(format!(
+                    "invalid redundant bits flag {}",
+                    flag
+                )));
+            }
+        };
+
+        // the count is not trusted for allocation, truncated input runs out first
+        let sample_count = u64::from_le_bytes(Self::read_bytes(reader)?);
+        let mut samples = Vec::new();
+        let mut infix_stores = Vec::new();
+        for _ in 0..sample_count {
+            let sample = K::read_le(reader)?;
+            if samples.last().is_some_and(|&previous| previous >= sample) {
+                return Err(Self::invalid_data(format!(
+                    "sample {} is out of order",
+                    sample
+                )));
+            }
+            samples.push(sample);
+
+            match Self::read_bytes(reader)? {
+                [0] => {}
+                [1] => {
+                    let elem_count = u16::from_le_bytes(Self::read_bytes(reader)?);
+                    let [size_grade] = Self::read_bytes(reader)?;
+                    let [quotient_size] = Self::read_bytes(reader)?;
+                    let word_count = u64::from_le_bytes(Self::read_bytes(reader)?);
+                    let mut data = Vec::new();
+                    for _ in 0..word_count {
+                        data.push(u64::from_le_bytes(Self::read_bytes(reader)?));
+                    }
+
+                    let infix_store = InfixStore::from_raw_parts(
+                        elem_count as usize,
+                        size_grade,
+                        quotient_size,
+                        remainder_size,
+                        data,
+                    )
+                    .ok_or_else(|| {
+                        Self::invalid_data(format!("invalid infix store for sample {}", sample))
+                    })?;
+                    infix_stores.push((sample, infix_store));
+                }
+                [flag] => {
+                    return Err(Self::invalid_data(format!(
+                        "invalid infix store flag {}",
+                        flag
+                    )));
+                }
+            }
+        }
+
+        let mut y_fast_trie = YFastTrie::new_with_keys(&samples, K::BITS);
+        for (sample, infix_store) in infix_stores {
+            y_fast_trie.set_infix_store(sample, infix_store);
+        }
+
+        Ok(Self {
+            y_fast_trie,
+            target_size,
+            fpr,
+            remainder_size,
+            use_redundant_bits,
+        })
+    }
+
+    fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn invalid_data(message: String) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    /// calculate remainder size based on FPR
+    /// FPR ≈ 2 / 2^remainder_size
+    pub(crate) fn choose_remainder_size(_target_size: usize, fpr: f64) -> u8 {
+        // remainder_size = log2(2/FPR) = log2(2) + log2(1/FPR) = 1 - log2(FPR)
+        let remainder_size = (1.0 - fpr.log2()).ceil() as u8;
+        remainder_size.max(4).min(16) // clamp between 4 and 16 bits
+    }
+
+    pub fn pretty_print(&self) {
+        print!("{}", self);
+    }
+}
+
+impl<K: UnsignedKey> RangeFilter<K> for Diva<K> {
+    type Config = DivaConfig;
+
+    fn from_keys(keys: &[K], config: &DivaConfig) -> Self {
+        Self::new_with_config(keys, config)
+    }
+
+    fn may_contain(&self, key: K) -> bool {
+        Diva::may_contain(self, key)
+    }
+
+    fn may_contain_range(&self, lo: K, hi: K) -> bool {
+        Diva::may_contain_range(self, lo, hi)
+    }
+
+    fn size_in_bits(&self) -> usize {
+        Diva::size_in_bits(self)
+    }
+
+    fn supports_insert(&self) -> bool {
+        true
+    }
+
+    fn insert(&mut self, key: K) -> Result<(), Unsupported> {
+        Diva::insert(self, key);
+        Ok(())
+    }
+
+    fn supports_delete(&self) -> bool {
+        true
+    }
+
+    fn delete(&mut self, key: K) -> Result<bool, Unsupported> {
+        Ok(Diva::delete(self, key))
+    }
+}
+
+impl<K: UnsignedKey> fmt::Display for Diva<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "\n╔════════════════════════════════════════════════════════╗"
+        )?;
+        writeln!(
+            f,
+            "║                   DIVA RANGE FILTER                    ║"
+        )?;
+        writeln!(
+            f,
+            "╚════════════════════════════════════════════════════════╝"
+        )?;
+
+        // configuration
+        writeln!(f, "\nConfiguration:")?;
+        writeln!(f, "  Target size (T):      {}", self.target_size)?;
+        writeln!(f, "  False positive rate:  {:.4}%", self.fpr * 100.0)?;
+        writeln!(f, "  Remainder size:       {} bits", self.remainder_size)?;
+        writeln!(f, "  Redundant bits:       {}", self.use_redundant_bits)?;
+
+        // stats
+        writeln!(f, "\nStatistics:")?;
+        writeln!(f, "  Total keys:           {}", self.y_fast_trie.len())?;
+        writeln!(
+            f,
+            "  Sample count:         {}",
+            self.y_fast_trie.sample_count()
+        )?;
+        let avg_bucket_size = if self.y_fast_trie.sample_count() > 0 {
+            self.y_fast_trie.len() as f64 / self.y_fast_trie.sample_count() as f64
+        } else {
+            0.0
+        };
+        writeln!(f, "  Avg keys per bucket:  {:.1}", avg_bucket_size)?;
+
+        // memory
+        writeln!(f, "\nMemory:")?;
+        write!(f, "{}", self.memory_usage())?;
+
+        // underlying Y-Fast Trie structure
+        write!(f, "{}", self.y_fast_trie)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_gen::{DataGen, RangeLenDistribution};
+    use crate::evaluate;
+    use std::collections::{BTreeSet, HashSet};
+
+    #[test]
+    fn test_choose_remainder_size() {
+        // FPR = 1% -> remainder_size = 8
+        assert_eq!(Diva::<Key>::choose_remainder_size(1024, 0.01), 8);
+        // FPR = 0.1% -> remainder_size = 11
+        assert_eq!(Diva::<Key>::choose_remainder_size(1024, 0.001), 11);
+        assert_eq!(Diva::<Key>::choose_remainder_size(1024, 0.1), 5);
+    }
+
+    #[test]
+    fn test_extraction_params() {
+        let key1 = 0b0000_0000_1111_0000u64 << 48;
+        let key2 = 0b0000_0000_1111_1111u64 << 48;
+
+        let (shared, redundant, quotient) =
+            Diva::get_shared_ignore_implicit_size(&key1, &key2, true, 10);
+
+        assert_eq!(shared, 12); // 12 bits shared prefix
+        assert_eq!(redundant, 0);
+        assert_eq!(quotient, 10); // the store's quotient size
+
+        // the quotient never reaches past the last key bit
+        let (_, _, quotient) =
+            Diva::get_shared_ignore_implicit_size(&(key1 >> 48), &(key2 >> 48), true, 16);
+        assert_eq!(quotient, 4);
+    }
+
+    #[test]
+    fn test_redundant_bits() {
+        // pred has 111 and succ has 000 right after the MSB
+        let predecessor = 0b0101_1100_0000_0000u16;
+        let successor = 0b0110_0011_0000_0000u16;
+        assert_eq!(
+            Diva::get_shared_ignore_implicit_size(&predecessor, &successor, true, 10),
+            (2, 3, 10)
+        );
+        assert_eq!(
+            Diva::get_shared_ignore_implicit_size(&predecessor, &successor, false, 10),
+            (2, 0, 10)
+        );
+        assert_eq!(
+            Diva::get_shared_ignore_implicit_size(&predecessor, &successor, true, 16),
+            (2, 3, 11)
+        );
+
+        // every key between the samples is rebuilt from its infix, skipped bits included
+        for key in predecessor..=successor {
+            let infix = Diva::extract_infix(key, predecessor, successor, 10, 4, true);
+            let (lowest, highest) =
+                Diva::infix_key_range(infix, predecessor, successor, 10, 4, true);
+            assert!(lowest <= key && key <= highest, "key {:016b}", key);
+        }
+    }
+
+    #[test]
+    fn test_redundant_bits_fpr() {
+        let mut data_gen = DataGen::new(42);
+        // buckets straddling 2^40 have pred = 0111.. and succ = 1000.. below the shared prefix
+        let center = 1u64 << 40;
+        let keys = data_gen.uniform_u64(8192, center - (1 << 24), center + (1 << 24));
+        let key_set: HashSet<u64> = keys.iter().copied().collect();
+        let probes: Vec<u64> = data_gen
+            .uniform_u64(20000, center - (1 << 20), center + (1 << 20))
+            .into_iter()
+            .filter(|probe| !key_set.contains(probe))
+            .collect();
+
+        let false_positive_rate = |use_redundant_bits: bool| {
+            let config = DivaConfig {
+                target_size: 1024,
+                fpr: 0.01,
+                use_redundant_bits,
+                ..DivaConfig::default()
+            };
+            let diva = Diva::new_with_config(&keys, &config);
+            for &key in &keys {
+                assert!(diva.may_contain(key));
+                assert!(diva.may_contain_range(key - 100, key + 100));
+            }
+            let false_positives = probes
+                .iter()
+                .filter(|&&probe| diva.may_contain(probe))
+                .count();
+            false_positives as f64 / probes.len() as f64
+        };
+
+        let plain = false_positive_rate(false);
+        let skipped = false_positive_rate(true);
+        assert!(skipped < plain / 2.0, "plain {} skipped {}", plain, skipped);
+    }
+
+    #[test]
+    fn test_quotient_size_follows_density() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(40_000, 0, u64::MAX);
+        let key_set: HashSet<u64> = keys.iter().copied().collect();
+        let probes: Vec<u64> = data_gen
+            .uniform_u64(50_000, 0, u64::MAX)
+            .into_iter()
+            .filter(|probe| !key_set.contains(probe))
+            .collect();
+
+        for (target_size, expected_quotient_size) in [(16, 4), (2048, 11)] {
+            let diva = Diva::new_with_keys(&keys, target_size, 0.01);
+            let samples = diva.y_fast_trie.keys();
+            let first_store = diva.y_fast_trie.get_infix_store(samples[0]).unwrap();
+            assert_eq!(
+                first_store.read().unwrap().quotient_size(),
+                expected_quotient_size
+            );
+
+            for &key in &keys {
+                assert!(diva.may_contain(key), "false negative for {}", key);
+            }
+            let false_positives = probes
+                .iter()
+                .filter(|&&probe| diva.may_contain(probe))
+                .count();
+            let fpr = false_positives as f64 / probes.len() as f64;
+            assert!(fpr < 0.02, "target size {} fpr {}", target_size, fpr);
+        }
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(100_000, 0, u64::MAX);
+        let key_set: BTreeSet<u64> = keys.iter().copied().collect();
+
+        let mut previous_fpr = 1.0;
+        for bits_per_key in [8.0, 12.0] {
+            for range_len in [1u64, 1 << 40] {
+                let budget = MemoryBudget::BitsPerKey(bits_per_key);
+                let plan = Diva::plan_for_budget(&keys, budget, range_len).unwrap();
+                assert!(plan.bits_per_key <= bits_per_key);
+                assert_eq!(
+                    plan.quotient_size,
+                    InfixStore::choose_quotient_size(plan.config.target_size - 1)
+                );
+
+                let diva = Diva::new_with_config(&keys, &plan.config);
+                let actual_bits = diva.size_in_bits() as f64 / keys.len() as f64;
+                assert!(actual_bits <= bits_per_key * 1.01, "{} bits", actual_bits);
+
+                // empty ranges only
+                let probes: Vec<u64> = data_gen
+                    .uniform_u64(20_000, 0, u64::MAX - range_len)
+                    .into_iter()
+                    .filter(|&lo| key_set.range(lo..lo + range_len).next().is_none())
+                    .collect();
+                let false_positives = probes
+                    .iter()
+                    .filter(|&&lo| diva.may_contain_range(lo, lo + (range_len - 1)))
+                    .count();
+                let fpr = false_positives as f64 / probes.len() as f64;
+                assert!(
+                    fpr < plan.expected_fpr * 2.0 && fpr > plan.expected_fpr / 4.0,
+                    "expected {} measured {}",
+                    plan.expected_fpr,
+                    fpr
+                );
+            }
+            let plan = Diva::plan_for_budget(&keys, MemoryBudget::BitsPerKey(bits_per_key), 1);
+            assert!(plan.unwrap().expected_fpr < previous_fpr);
+            previous_fpr = plan.unwrap().expected_fpr;
+        }
+
+        // a byte budget is spread over the keys
+        assert_eq!(
+            Diva::plan_for_budget(&keys, MemoryBudget::TotalBytes(150_000), 1),
+            Diva::plan_for_budget(&keys, MemoryBudget::BitsPerKey(12.0), 1)
+        );
+        assert!(Diva::plan_for_budget(&keys, MemoryBudget::BitsPerKey(2.0), 1).is_none());
+        assert!(Diva::new_with_budget(&keys[..1000], MemoryBudget::BitsPerKey(12.0), 1).is_some());
+    }
+
+    #[test]
+    fn test_estimated_fpr() {
+        let mut data_gen = DataGen::new(42);
+        let datasets = [
+            (data_gen.uniform_u64(30_000, 0, u64::MAX), 1024, 0.01),
+            (data_gen.uniform_u64(30_000, 0, u64::MAX), 64, 0.001),
+            (data_gen.normal_u64(30_000, 1e15, 1e12), 1024, 0.01),
+        ];
+        for (keys, target_size, fpr) in &datasets {
+            let key_set: BTreeSet<u64> = keys.iter().copied().collect();
+            let (min, max) = (*key_set.first().unwrap(), *key_set.last().unwrap());
+            let diva = Diva::new_with_keys(keys, *target_size, *fpr);
+
+            for range_len in [1u64, 1 << 20] {
+                let probes: Vec<u64> = data_gen
+                    .uniform_u64(30_000, min, max - range_len)
+                    .into_iter()
+                    .filter(|&lo| key_set.range(lo..lo + range_len).next().is_none())
+                    .collect();
+                let false_positives = probes
+                    .iter()
+                    .filter(|&&lo| diva.may_contain_range(lo, lo + (range_len - 1)))
+                    .count();
+                let measured = false_positives as f64 / probes.len() as f64;
+                let estimated = diva.estimated_fpr(&[(range_len, 1.0)]);
+                assert!(
+                    (measured - estimated).abs() < estimated * 0.3 + 0.001,
+                    "estimated {} measured {}",
+                    estimated,
+                    measured
+                );
+            }
+        }
+
+        // a query mix is weighted by its frequencies
+        let diva = Diva::new_with_keys(&datasets[2].0, 1024, 0.01);
+        let point = diva.estimated_fpr(&[(1, 1.0)]);
+        let long = diva.estimated_fpr(&[(1 << 32, 1.0)]);
+        let mixed = diva.estimated_fpr(&[(1, 1.0), (1 << 32, 3.0)]);
+        assert!((mixed - (point + 3.0 * long) / 4.0).abs() < 1e-12);
+
+        let empty: Diva = Diva::new(1024, 0.01);
+        assert_eq!(empty.estimated_fpr(&[(1, 1.0)]), 0.0);
+    }
+
+    #[test]
+    fn test_shared_prefix_keys() {
+        // groups of keys differing only in their low bits, built and then updated
+        let mut data_gen = DataGen::new(17);
+        for (num_groups, suffix_bits) in [(4, 12), (64, 8), (3, 40)] {
+            let keys = data_gen.shared_prefix_u64(20_000, num_groups, suffix_bits);
+            let (built, inserted) = keys.split_at(10_000);
+            let mut diva = Diva::new_with_keys(built, 256, 0.01);
+            for &key in inserted {
+                diva.insert(key);
+            }
+
+            let range_len = RangeLenDistribution::LogUniform {
+                min_log2: 0,
+                max_log2: suffix_bits / 2,
+            };
+            let empty_queries = data_gen.empty_queries(10_000, &keys, range_len);
+            let correlated_queries = data_gen.correlated_queries(10_000, &keys, 4, range_len);
+
+            // no false negatives on any key or non-empty query
+            let key_set: BTreeSet<u64> = keys.iter().copied().collect();
+            for &key in &keys {
+                assert!(diva.may_contain(key), "false negative for key {}", key);
+            }
+            let mut non_empty_queries = 0;
+            for &(lo, hi) in &correlated_queries {
+                if key_set.range(lo..=hi).next().is_some() {
+                    assert!(
+                        diva.may_contain_range(lo, hi),
+                        "false negative for range [{}, {}]",
+                        lo,
+                        hi
+                    );
+                    non_empty_queries += 1;
+                }
+            }
+            assert!(non_empty_queries > 0);
+
+            // empty queries next to the groups stay within twice the configured FPR
+            let evaluation = evaluate(&diva, &keys, empty_queries);
+            assert_eq!(evaluation.empty_queries(), 10_000);
+            assert!(evaluation.fpr() < 0.02, "fpr {}", evaluation.fpr());
+        }
+    }
+
+    #[test]
+    fn test_construction_small_dataset() {
+        // 100 keys, all fit in one sample
+        let keys: Vec<u64> = (0..100).map(|i| i * 1000).collect();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        assert_eq!(diva.target_size, 1024);
+        assert_eq!(diva.fpr, 0.01);
+        assert_eq!(diva.remainder_size, 8);
+    }
+
+    #[test]
+    fn test_construction_with_sampling() {
+        // 5000 keys - should create ~5 samples
+        let keys: Vec<u64> = (0..5000).map(|i| i as u64).collect();
+        let target_size = 1024;
+        let diva = Diva::new_with_keys(&keys, target_size, 0.01);
+
+        // +1 because we sample the last key too
+        let expected_samples = (keys.len() + target_size - 1) / target_size + 1;
+        let actual_samples = diva.y_fast_trie.len();
+
+        assert_eq!(actual_samples, expected_samples);
+    }
+
+    #[test]
+    fn test_construction_single_sample() {
+        // 500 keys < target_size -> only 1 sample
+        let keys: Vec<u64> = (0..500).map(|i| i * 10).collect();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        assert_eq!(diva.y_fast_trie.sample_count(), 1);
+    }
+
+    #[test]
+    fn test_range_query_no_false_negatives() {
+        let keys = DataGen::new(42).uniform_u64(20_000, 0, u64::MAX);
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        for &key in &keys {
+            assert!(
+                diva.may_contain_range(key, key),
+                "false negative for {}",
+                key
+            );
+            assert!(
+                diva.may_contain_range(key.saturating_sub(1000), key.saturating_add(1000)),
+                "false negative around {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_range_query_spanning_samples() {
+        let keys: Vec<u64> = (0..5000).map(|i| i * 7).collect();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        // ranges covering several buckets
+        assert!(diva.may_contain_range(0, 34_993));
+        assert!(diva.may_contain_range(1, 20_000));
+        assert!(diva.may_contain_range(7 * 1000 + 1, 7 * 3000 - 1));
+    }
+
+    #[test]
+    fn test_range_query_empty_ranges() {
+        let keys: Vec<u64> = (1..5000).map(|i| i * 1000).collect();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        // outside the key range
+        assert!(!diva.may_contain_range(0, 999));
+        assert!(!diva.may_contain_range(5_000_000, u64::MAX));
+
+        // inverted range
+        assert!(!diva.may_contain_range(2000, 1000));
+
+        // gaps between keys are wider than the infix resolution
+        let false_positives = keys
+            .iter()
+            .filter(|&&key| diva.may_contain_range(key + 100, key + 900))
+            .count();
+        assert_eq!(false_positives, 0);
+    }
+
+    #[test]
+    fn test_point_query_no_false_negatives() {
+        let keys = DataGen::new(42).uniform_u64(20_000, 0, u64::MAX);
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        for &key in &keys {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+    }
+
+    #[test]
+    fn test_point_query_samples_and_gaps() {
+        let keys: Vec<u64> = (1..5000).map(|i| i * 1000).collect();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        // samples are answered exactly
+        assert!(diva.may_contain(1000));
+        assert!(diva.may_contain(1025 * 1000));
+        assert!(diva.may_contain(4999 * 1000));
+
+        // outside the key range
+        assert!(!diva.may_contain(0));
+        assert!(!diva.may_contain(5_000_000));
+
+        // keys in the gaps map to infixes no inserted key has
+        let false_positives = keys
+            .iter()
+            .filter(|&&key| diva.may_contain(key + 500))
+            .count();
+        assert_eq!(false_positives, 0);
+    }
+
+    #[test]
+    fn test_insert_into_empty_filter() {
+        let mut diva: Diva = Diva::new(1024, 0.01);
+        assert!(!diva.may_contain(42));
+
+        diva.insert(42);
+        assert!(diva.may_contain(42));
+        assert!(diva.may_contain_range(0, 100));
+        assert!(!diva.may_contain_range(0, 41));
+        assert!(!diva.may_contain_range(43, 100));
+
+        // keys outside the sampled range become samples
+        diva.insert(10);
+        diva.insert(1000);
+        assert_eq!(diva.y_fast_trie.len(), 3);
+
+        // keys between samples go to an infix store
+        diva.insert(500);
+        assert_eq!(diva.y_fast_trie.len(), 3);
+        for key in [10, 42, 500, 1000] {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+    }
+
+    #[test]
+    fn test_insert_no_false_negatives() {
+        let keys = DataGen::new(42).uniform_u64(10_000, 0, u64::MAX);
+        let target_size = 256;
+        let mut diva: Diva = Diva::new(target_size, 0.01);
+        for &key in &keys {
+            diva.insert(key);
+        }
+
+        // full stores were split by promoting new samples
+        assert!(diva.y_fast_trie.len() > keys.len() / target_size);
+
+        for &key in &keys {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+            assert!(
+                diva.may_contain_range(key.saturating_sub(1000), key.saturating_add(1000)),
+                "false negative around {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_into_built_filter() {
+        let keys: Vec<u64> = (1..5000).map(|i| i * 1000).collect();
+        let mut diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        let new_keys: Vec<u64> = (1..5000).map(|i| i * 1000 + 500).collect();
+        for &key in &new_keys {
+            diva.insert(key);
+        }
+
+        for &key in keys.iter().chain(&new_keys) {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+
+        // split stores keep enough precision to tell the gaps apart
+        let false_positives = keys
+            .iter()
+            .filter(|&&key| diva.may_contain_range(key + 100, key + 400))
+            .count();
+        assert!(
+            false_positives < keys.len() / 10,
+            "{} false positives",
+            false_positives
+        );
+    }
+
+    #[test]
+    fn test_delete_infixes() {
+        let keys: Vec<u64> = (1..5000).map(|i| i * 1000).collect();
+        let mut diva = Diva::new_with_keys(&keys, 1024, 0.01);
+
+        let deleted: Vec<u64> = keys
+            .iter()
+            .copied()
+            .filter(|&key| key % 2000 == 0 && !diva.y_fast_trie.contains(key))
+            .collect();
+        for &key in &deleted {
+            assert!(diva.delete(key), "failed to delete {}", key);
+        }
+
+        // deleting again or deleting keys that were never inserted is reported,
+        // as long as they do not collide with a stored key
+        assert!(!diva.delete(deleted[0]));
+        assert!(!diva.delete(1500));
+        assert!(!diva.delete(u64::MAX));
+
+        for &key in keys.iter().filter(|key| !deleted.contains(key)) {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+        let false_positives = deleted.iter().filter(|&&key| diva.may_contain(key)).count();
+        assert!(
+            false_positives < deleted.len() / 10,
+            "{} false positives",
+            false_positives
+        );
+    }
+
+    #[test]
+    fn test_insert_clustered_keys() {
+        // a narrow cluster in a wide bucket: all keys share a few infixes, which no
+        // sample between them can separate
+        let mut diva = Diva::new_with_keys(&[0u64, 1 << 40], 3, 0.01);
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(3 * MAX_ELEMENTS, (1 << 39) - 25_000, (1 << 39) + 25_000);
+        for &key in &keys {
+            diva.insert(key);
+        }
+        for &key in &keys {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+        assert!(diva.stats().max_bucket_size <= MAX_ELEMENTS);
+        assert_eq!(diva.validate_infix_stores(), Ok(()));
+
+        // infixes merged to fit a full store cover the remaining keys after deletes
+        for &key in &keys[..keys.len() / 2] {
+            diva.delete(key);
+        }
+        for &key in &keys[keys.len() / 2..] {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+    }
+
+    #[test]
+    fn test_delete_samples() {
+        let keys: Vec<u64> = (1..5000).map(|i| i * 1000).collect();
+        let mut diva = Diva::new_with_keys(&keys, 1024, 0.01);
+        let samples: Vec<u64> = keys
+            .iter()
+            .copied()
+            .filter(|&key| diva.y_fast_trie.contains(key))
+            .collect();
+        assert!(samples.len() > 2);
+
+        // the first and last samples are replaced, interior ones merge their buckets
+        for &sample in &samples {
+            assert!(diva.delete(sample));
+            assert!(!diva.y_fast_trie.contains(sample));
+        }
+
+        for &key in keys.iter().filter(|key| !samples.contains(key)) {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+            assert!(diva.may_contain_range(key - 10, key + 10));
+        }
+    }
+
+    #[test]
+    fn test_delete_no_false_negatives() {
+        let keys = DataGen::new(42).uniform_u64(4000, 0, u64::MAX);
+        let mut diva: Diva = Diva::new(256, 0.01);
+        for &key in &keys {
+            diva.insert(key);
+        }
+
+        // delete every other key, samples included
+        for key in keys.iter().step_by(2) {
+            diva.delete(*key);
+        }
+
+        for key in keys.iter().skip(1).step_by(2) {
+            assert!(diva.may_contain(*key), "false negative for {}", key);
+        }
+        // splits, merges and updates in place leave every store consistent
+        assert_eq!(diva.validate_infix_stores(), Ok(()));
+    }
+
+    fn serialized<K: UnsignedKey>(diva: &Diva<K>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        diva.serialize_into(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(10_000, 0, u64::MAX);
+        let mut diva = Diva::new_with_keys(&keys[..5000], 1024, 0.01);
+        for &key in &keys[5000..] {
+            diva.insert(key);
+        }
+
+        let bytes = serialized(&diva);
+        assert_eq!(&bytes[..4], b"DIVA");
+
+        let restored = Diva::<Key>::deserialize_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(restored.target_size, diva.target_size);
+        assert_eq!(restored.fpr, diva.fpr);
+        assert_eq!(restored.remainder_size, diva.remainder_size);
+        assert_eq!(restored.y_fast_trie.keys(), diva.y_fast_trie.keys());
+        assert_eq!(serialized(&restored), bytes);
+
+        for &key in &keys {
+            assert!(restored.may_contain(key), "false negative for {}", key);
+        }
+        let probes = data_gen.uniform_u64(1000, 0, u64::MAX);
+        for &probe in &probes {
+            assert_eq!(restored.may_contain(probe), diva.may_contain(probe));
+            let hi = probe.saturating_add(1 << 40);
+            assert_eq!(
+                restored.may_contain_range(probe, hi),
+                diva.may_contain_range(probe, hi)
+            );
+        }
+
+        // the redundant bits flag round-trips
+        let config = DivaConfig {
+            use_redundant_bits: false,
+            ..DivaConfig::default()
+        };
+        let plain = Diva::new_with_config(&keys, &config);
+        let bytes = serialized(&plain);
+        let restored = Diva::<Key>::deserialize_from(&mut bytes.as_slice()).unwrap();
+        assert!(!restored.use_redundant_bits);
+        assert_eq!(serialized(&restored), bytes);
+
+        // an empty filter round-trips too
+        let empty: Diva = Diva::new(1024, 0.01);
+        let restored = Diva::<Key>::deserialize_from(&mut serialized(&empty).as_slice()).unwrap();
+        assert_eq!(restored.y_fast_trie.len(), 0);
+    }
+
+    #[test]
+    fn test_deserialize_truncated() {
+        let keys: Vec<u64> = (0..60).map(|i| i * 1000).collect();
+        let bytes = serialized(&Diva::new_with_keys(&keys, 16, 0.01));
+
+        for len in 0..bytes.len() {
+            let error = Diva::<Key>::deserialize_from(&mut &bytes[..len])
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_corrupted() {
+        let keys: Vec<u64> = (0..60).map(|i| i * 1000).collect();
+        let bytes = serialized(&Diva::new_with_keys(&keys, 16, 0.01));
+        let corrupt = |offset: usize, value: u8| {
+            let mut corrupted = bytes.clone();
+            corrupted[offset] = value;
+            Diva::<Key>::deserialize_from(&mut corrupted.as_slice()).err()
+        };
+
+        // header: magic, version (0 and 2), key_bits, target_size (0 and 4112), fpr,
+        // remainder_size (0, 1 and 49), use_redundant_bits
+        for (offset, value) in [
+            (0, b'X'),
+            (4, 0),
+            (4, 2),
+            (6, 32),
+            (7, 0),
+            (8, 0x10),
+            (21, 0xff),
+            (23, 0),
+            (23, MIN_REMAINDER_SIZE - 1),
+            (23, MAX_REMAINDER_SIZE + 1),
+            (24, 2),
+        ] {
+            let error = corrupt(offset, value).expect("corruption not detected");
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // the second sample key is 16000, zeroing it breaks the order
+        // first sample: key at 33, flag at 41, store header at 42..54, data words from 54
+        let word_count = u64::from_le_bytes(bytes[46..54].try_into().unwrap()) as usize;
+        let second_sample = 54 + 8 * word_count;
+        assert_eq!(
+            u64::from_le_bytes(bytes[second_sample..second_sample + 8].try_into().unwrap()),
+            16000
+        );
+        let mut unordered = bytes.clone();
+        unordered[second_sample..second_sample + 8].fill(0);
+        assert!(Diva::<Key>::deserialize_from(&mut unordered.as_slice()).is_err());
+
+        // infix store flag, quotient size, then a flipped occupieds bit inside the first store
+        assert!(corrupt(41, 2).is_some());
+        assert!(corrupt(45, MAX_QUOTIENT_SIZE + 1).is_some());
+        assert!(corrupt(54 + 8, bytes[54 + 8] ^ 1).is_some());
+    }
+
+    #[test]
+    fn test_insert_largest_target_size() {
+        // dense keys fill the largest size grade of a store before its bucket splits
+        let mut diva: Diva = Diva::new(MAX_ELEMENTS, 0.01);
+        let mut keys = vec![0, Key::MAX];
+        keys.extend(DataGen::new(42).uniform_u64(3 * MAX_ELEMENTS, 1, Key::MAX - 1));
+        for &key in &keys {
+            diva.insert(key);
+        }
+
+        assert!(keys.iter().all(|&key| diva.may_contain(key)));
+        assert!(diva.y_fast_trie.len() > 2);
+        assert!(diva.stats().max_bucket_size <= MAX_ELEMENTS);
+        assert_eq!(diva.validate_infix_stores(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "remainder size 0 is not in 2..=48")]
+    fn test_remainder_size_zero() {
+        let config = DivaConfig {
+            remainder_size: Some(0),
+            ..DivaConfig::default()
+        };
+        Diva::new_with_config(&[1u64, 2, 3], &config);
+    }
+
+    #[test]
+    #[should_panic(expected = "remainder size 60 is not in 2..=48")]
+    fn test_remainder_size_too_large() {
+        let config = DivaConfig {
+            remainder_size: Some(60),
+            ..DivaConfig::default()
+        };
+        Diva::new_with_config(&[1u64, 2, 3], &config);
+    }
+
+    #[test]
+    fn test_remainder_size_bounds() {
+        let keys = DataGen::new(42).uniform_u64(10_000, 0, u64::MAX);
+        for remainder_size in [MIN_REMAINDER_SIZE, MAX_REMAINDER_SIZE] {
+            let config = DivaConfig {
+                target_size: 64,
+                remainder_size: Some(remainder_size),
+                ..DivaConfig::default()
+            };
+            let mut diva = Diva::new_with_config(&keys, &config);
+            check_range_filter(&mut diva, &keys);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "target size 4096 is not in 1..=2326")]
+    fn test_target_size_too_large() {
+        let keys = DataGen::new(42).uniform_u64(10_000, 0, u64::MAX);
+        Diva::new_with_keys(&keys, 4096, 0.01);
+    }
+
+    fn check_range_filter<F: RangeFilter>(filter: &mut F, keys: &[Key]) {
+        for &key in keys {
+            assert!(filter.may_contain(key));
+            assert!(filter.may_contain_range(key.saturating_sub(5), key.saturating_add(5)));
+        }
+        assert!(filter.size_in_bits() > 0);
+
+        if filter.supports_insert() {
+            assert_eq!(filter.insert(7), Ok(()));
+            assert!(filter.may_contain(7));
+        }
+        if filter.supports_delete() {
+            assert_eq!(filter.delete(7), Ok(true));
+            assert_eq!(filter.delete(7), Ok(false));
+        }
+    }
+
+    #[test]
+    fn test_range_filter_trait() {
+        let keys: Vec<u64> = (1..5000).map(|i| i * 1000).collect();
+        let config = DivaConfig::default();
+        let mut diva = <Diva as RangeFilter>::from_keys(&keys, &config);
+        assert!(diva.supports_insert() && diva.supports_delete());
+        check_range_filter(&mut diva, &keys);
+
+        // samples plus one data vector per bucket
+        let samples = diva.y_fast_trie.sample_count();
+        assert!(diva.size_in_bits() > samples * 64);
+        assert!(diva.size_in_bits() < keys.len() * 64);
+    }
+
+    /// no false negatives around every key, and few positives on random empty ranges
+    fn check_key_width<K: UnsignedKey>(diva: &Diva<K>, keys: &[K], probes: &[K], width: K) {
+        for &key in keys {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+            let lo = key.checked_sub(width).unwrap_or(K::ZERO);
+            let hi = key.checked_add(width).unwrap_or(K::MAX);
+            assert!(
+                diva.may_contain_range(lo, hi),
+                "false negative around {}",
+                key
+            );
+        }
+
+        let mut sorted_keys = keys.to_vec();
+        sorted_keys.sort();
+        let mut empty_ranges = 0;
+        let mut false_positives = 0;
+        for &lo in probes {
+            let Some(hi) = lo.checked_add(width) else {
+                continue;
+            };
+            let next = sorted_keys.partition_point(|&key| key < lo);
+            if next < sorted_keys.len() && sorted_keys[next] <= hi {
+                continue;
+            }
+            empty_ranges += 1;
+            if diva.may_contain_range(lo, hi) {
+                false_positives += 1;
+            }
+        }
+        assert!(empty_ranges > 0);
+        assert!(
+            false_positives * 10 < empty_ranges,
+            "{} false positives in {} empty ranges",
+            false_positives,
+            empty_ranges
+        );
+    }
+
+    #[test]
+    fn test_u32_keys() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u32(20_000, 0, u32::MAX);
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+        assert_eq!(diva.y_fast_trie.x_fast_trie.no_levels, 32);
+
+        let probes = data_gen.uniform_u32(2000, 0, u32::MAX);
+        check_key_width(&diva, &keys, &probes, 100);
+    }
+
+    #[test]
+    fn test_u16_keys() {
+        let keys: Vec<u16> = (0..4000u16).map(|i| i * 13 + (i % 5)).collect();
+        let mut diva = Diva::new_with_keys(&keys, 256, 0.01);
+        assert_eq!(diva.y_fast_trie.x_fast_trie.no_levels, 16);
+
+        for key in [3, 11, 60_000] {
+            diva.insert(key);
+        }
+        for &key in keys.iter().chain(&[3, 11, 60_000]) {
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+        assert!(diva.delete(60_000));
+    }
+
+    #[test]
+    fn test_u128_keys() {
+        let mut data_gen = DataGen::new(42);
+        // time-ordered ids sharing their top 64 bits
+        let high = 0x0123_4567_89ab_cdef_u128 << 64;
+        let keys: Vec<u128> = data_gen
+            .uniform_u64(20_000, 0, u64::MAX)
+            .into_iter()
+            .map(|low| high | low as u128)
+            .collect();
+
+        let mut diva = Diva::new_with_keys(&keys, 1024, 0.01);
+        assert_eq!(diva.y_fast_trie.x_fast_trie.no_levels, 128);
+
+        let probes: Vec<u128> = data_gen
+            .uniform_u64(2000, 0, u64::MAX)
+            .into_iter()
+            .map(|low| high | low as u128)
+            .collect();
+        check_key_width(&diva, &keys, &probes, 1 << 40);
+
+        // keys far outside the shared prefix become new first and last samples
+        for key in [1, 1 << 100, high | (u64::MAX as u128 - 3), u128::MAX] {
+            diva.insert(key);
+            assert!(diva.may_contain(key), "false negative for {}", key);
+        }
+
+        // serialized keys keep their width and cannot be read back at another one
+        let bytes = serialized(&diva);
+        let restored = Diva::<u128>::deserialize_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(restored.y_fast_trie.keys(), diva.y_fast_trie.keys());
+        let error = Diva::<u64>::deserialize_from(&mut bytes.as_slice())
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_encoded_keys() {
+        // signed keys straddling zero
+        let keys = DataGen::new(42).smooth_i32(Some(20000));
+        let diva = Diva::new_with_encoded_keys(&keys, 1024, 0.01);
+        for &key in &keys {
+            assert!(diva.may_contain_encoded(key));
+            assert!(diva.may_contain_range_encoded(key.saturating_sub(3), key.saturating_add(3)));
+        }
+        let min = *keys.iter().min().unwrap();
+        let max = *keys.iter().max().unwrap();
+        if let Some(below) = min.checked_sub(1) {
+            assert!(!diva.may_contain_range_encoded(i32::MIN, below));
+        }
+        if let Some(above) = max.checked_add(1) {
+            assert!(!diva.may_contain_range_encoded(above, i32::MAX));
+        }
+
+        // floats, including negative ranges
+        let floats: Vec<f64> = keys.iter().map(|&k| k as f64 / 1000.0).collect();
+        let mut diva: Diva = Diva::new_with_encoded_keys(&floats, 1024, 0.01);
+        for &key in &floats {
+            assert!(diva.may_contain_range_encoded(key - 0.0005, key + 0.0005));
+        }
+        assert!(!diva.may_contain_range_encoded(f64::NEG_INFINITY, min as f64 / 1000.0 - 1.0));
+        diva.insert_encoded(f64::INFINITY);
+        assert!(diva.may_contain_encoded(f64::INFINITY));
+
+        // composite keys
+        let pairs: Vec<(u32, u32)> = keys
+            .iter()
+            .map(|&k| ((k & 0xff) as u32, k as u32))
+            .collect();
+        let diva = Diva::new_with_encoded_keys(&pairs, 256, 0.01);
+        for &pair in &pairs {
+            assert!(diva.may_contain_range_encoded((pair.0, 0), (pair.0, u32::MAX)));
+        }
+        assert!(!diva.may_contain_range_encoded((256, 0), (u32::MAX, u32::MAX)));
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut keys = DataGen::new(42).uniform_u64(20000, 0, u64::MAX);
+        keys.sort();
+        keys.dedup();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+        let usage = diva.memory_usage();
+
+        assert_eq!(usage.key_count, keys.len());
+        assert_eq!(
+            usage.total_bytes(),
+            usage.x_fast_levels
+                + usage.rep_nodes
+                + usage.bst_nodes
+                + usage.infix_stores
+                + usage.filter_fields
+        );
+        assert!(usage.x_fast_levels > 0 && usage.rep_nodes > 0 && usage.bst_nodes > 0);
+
+        // the filter's own fields are counted apart from the trie levels
+        let trie_usage = diva.y_fast_trie.memory_usage();
+        assert_eq!(usage.x_fast_levels, trie_usage.x_fast_levels);
+        assert_eq!(
+            usage.filter_fields,
+            size_of::<Diva>() - size_of::<YFastTrie>()
+        );
+        assert_eq!(trie_usage.filter_fields, 0);
+
+        // infix stores hold at least the words counted by size_in_bits
+        let sample_bits = diva.y_fast_trie.len() * 64;
+        assert!(usage.infix_stores * 8 >= diva.size_in_bits() - sample_bits);
+        assert!(usage.bits_per_key() > (diva.size_in_bits() as f64 / keys.len() as f64));
+
+        let empty: Diva = Diva::new(1024, 0.01);
+        assert_eq!(empty.memory_usage().key_count, 0);
+        assert_eq!(empty.memory_usage().bits_per_key(), 0.0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut keys = DataGen::new(42).uniform_u64(20000, 0, u64::MAX);
+        keys.sort();
+        keys.dedup();
+        let diva = Diva::new_with_keys(&keys, 1024, 0.01);
+        let stats = diva.stats();
+
+        assert_eq!(stats.config.target_size, 1024);
+        assert_eq!(stats.config.remainder_size, Some(diva.remainder_size));
+        assert_eq!(stats.key_count(), keys.len());
+        assert_eq!(stats.sample_count, diva.y_fast_trie.len());
+        assert!(stats.infix_store_count > 0 && stats.infix_store_count <= stats.sample_count);
+        assert!(stats.max_bucket_size <= MAX_ELEMENTS);
+        assert!(stats.load_factor() > 0.0 && stats.load_factor() <= 1.0);
+        assert_eq!(
+            stats
+                .quotient_sizes
+                .iter()
+                .map(|&(_, count)| count)
+                .sum::<usize>(),
+            stats.infix_store_count
+        );
+        assert_eq!(stats.size_in_bits, diva.size_in_bits());
+        assert_eq!(stats.memory, diva.memory_usage());
+        assert!(stats.to_string().contains("Load factor"));
+
+        let empty: Diva = Diva::new(1024, 0.01);
+        let stats = empty.stats();
+        assert_eq!(stats.key_count(), 0);
+        assert_eq!(stats.load_factor(), 0.0);
+        assert!(stats.quotient_sizes.is_empty());
     }
 }
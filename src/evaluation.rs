@@ -0,0 +1,240 @@
+use crate::{RangeFilter, UnsignedKey};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Query counts of one range length class
+///
+/// Class `log2_max_len` holds the queries with a length in
+/// `(2^(log2_max_len - 1), 2^log2_max_len]`, class 0 holds the point queries.
+///
+/// # Arguments
+/// * `log2_max_len` - Log2 of the longest range in the class
+/// * `empty_queries` - Queries holding no key, by the exact oracle
+/// * `false_positives` - Empty queries the filter answered true
+/// * `non_empty_queries` - Queries holding a key, all answered true
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthClass {
+    pub log2_max_len: u32,
+    pub empty_queries: usize,
+    pub false_positives: usize,
+    pub non_empty_queries: usize,
+}
+
+impl LengthClass {
+    /// false positives among the empty queries, 0 without empty queries
+    pub fn fpr(&self) -> f64 {
+        if self.empty_queries == 0 {
+            return 0.0;
+        }
+        self.false_positives as f64 / self.empty_queries as f64
+    }
+}
+
+/// Result of running a filter against an exact oracle, see `evaluate`
+///
+/// # Arguments
+/// * `classes` - Counts per range length class, sorted by length, skipping unused classes
+/// * `num_keys` - Number of distinct keys the filter holds
+/// * `size_in_bits` - Size of the filter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub classes: Vec<LengthClass>,
+    pub num_keys: usize,
+    pub size_in_bits: usize,
+}
+
+impl Evaluation {
+    pub fn empty_queries(&self) -> usize {
+        self.classes.iter().map(|class| class.empty_queries).sum()
+    }
+
+    pub fn false_positives(&self) -> usize {
+        self.classes.iter().map(|class| class.false_positives).sum()
+    }
+
+    pub fn non_empty_queries(&self) -> usize {
+        self.classes
+            .iter()
+            .map(|class| class.non_empty_queries)
+            .sum()
+    }
+
+    /// false positives among all empty queries
+    pub fn fpr(&self) -> f64 {
+        if self.empty_queries() == 0 {
+            return 0.0;
+        }
+        self.false_positives() as f64 / self.empty_queries() as f64
+    }
+
+    pub fn bits_per_key(&self) -> f64 {
+        if self.num_keys == 0 {
+            return 0.0;
+        }
+        self.size_in_bits as f64 / self.num_keys as f64
+    }
+
+    /// counts of the class holding ranges of `range_len` keys
+    pub fn class_of_len(&self, range_len: u128) -> Option<&LengthClass> {
+        let log2_max_len = range_len.max(1).next_power_of_two().trailing_zeros();
+        self.classes
+            .iter()
+            .find(|class| class.log2_max_len == log2_max_len)
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "\n=== Evaluation ===")?;
+        writeln!(f, "  Keys:          {}", self.num_keys)?;
+        writeln!(f, "  Bits per key:  {:.2}", self.bits_per_key())?;
+        writeln!(f, "  Empty queries: {}", self.empty_queries())?;
+        writeln!(f, "  Other queries: {}", self.non_empty_queries())?;
+        writeln!(f, "  FPR:           {:.6}", self.fpr())?;
+        for class in &self.classes {
+            writeln!(
+                f,
+                "  len <= 2^{:<3} empty {:>8}  false positives {:>8}  fpr {:.6}",
+                class.log2_max_len,
+                class.empty_queries,
+                class.false_positives,
+                class.fpr()
+            )?;
+        }
+        writeln!(f, "=== End Evaluation ===\n")?;
+        Ok(())
+    }
+}
+
+/// Measure the FPR of a filter built over `keys`
+///
+/// Every query `(lo, hi)` is answered by the filter and by an exact oracle over the keys,
+/// points with `may_contain` and longer ranges with `may_contain_range`. Empty queries
+/// answered true are false positives. Queries with `lo > hi` are skipped.
+///
+/// # Panics
+/// On any false negative: a key the filter does not contain, or a non-empty query it
+/// answers false.
+///
+/// # Arguments
+/// * `filter` - Filter built over `keys`
+/// * `keys` - Keys the filter was built from
+/// * `queries` - Inclusive ranges to query
+pub fn evaluate<K: UnsignedKey, F: RangeFilter<K>>(
+    filter: &F,
+    keys: &[K],
+    queries: impl IntoIterator<Item = (K, K)>,
+) -> Evaluation {
+    let oracle: BTreeSet<K> = keys.iter().copied().collect();
+    for &key in &oracle {
+        assert!(filter.may_contain(key), "false negative for key {}", key);
+    }
+
+    // one class per bit length of hi - lo
+    let mut classes: Vec<LengthClass> = (0..=K::BITS as u32)
+        .map(|log2_max_len| LengthClass {
+            log2_max_len,
+            empty_queries: 0,
+            false_positives: 0,
+            non_empty_queries: 0,
+        })
+        .collect();
+
+    for (lo, hi) in queries {
+        if lo > hi {
+            continue;
+        }
+
+        let answer = if lo == hi {
+            filter.may_contain(lo)
+        } else {
+            filter.may_contain_range(lo, hi)
+        };
+        let class = &mut classes[K::BITS - (hi - lo).leading_zeros() as usize];
+        if oracle.range(lo..=hi).next().is_some() {
+            assert!(answer, "false negative for range [{}, {}]", lo, hi);
+            class.non_empty_queries += 1;
+        } else {
+            class.empty_queries += 1;
+            class.false_positives += answer as usize;
+        }
+    }
+
+    Evaluation {
+        classes: classes
+            .into_iter()
+            .filter(|class| class.empty_queries + class.non_empty_queries > 0)
+            .collect(),
+        num_keys: oracle.len(),
+        size_in_bits: filter.size_in_bits(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_gen::DataGen;
+    use crate::{Diva, DivaConfig, Key};
+
+    // answers true everywhere except a hole, to check false negatives are caught
+    struct LeakyFilter(Key);
+
+    impl RangeFilter for LeakyFilter {
+        type Config = Key;
+
+        fn from_keys(_keys: &[Key], hole: &Key) -> Self {
+            Self(*hole)
+        }
+
+        fn may_contain(&self, key: Key) -> bool {
+            key != self.0
+        }
+
+        fn may_contain_range(&self, lo: Key, hi: Key) -> bool {
+            !(lo == self.0 && hi == self.0)
+        }
+
+        fn size_in_bits(&self) -> usize {
+            64
+        }
+    }
+
+    #[test]
+    fn test_evaluate_diva() {
+        let mut data_gen = DataGen::new(42);
+        let keys = data_gen.uniform_u64(20_000, 0, 1 << 40);
+        let diva = Diva::from_keys(&keys, &DivaConfig::default());
+
+        let mut queries: Vec<(Key, Key)> = data_gen
+            .uniform_u64(20_000, 0, 1 << 40)
+            .into_iter()
+            .flat_map(|lo| [(lo, lo), (lo, lo + 99), (lo, lo + (1 << 20))])
+            .collect();
+        queries.extend(keys.iter().map(|&key| (key, key)));
+        queries.push((10, 5));
+        let evaluation = evaluate(&diva, &keys, queries);
+
+        let points = evaluation.class_of_len(1).unwrap();
+        assert_eq!(points.log2_max_len, 0);
+        assert!(points.non_empty_queries >= keys.len());
+        assert_eq!(evaluation.class_of_len(100).unwrap().log2_max_len, 7);
+        assert!(evaluation.class_of_len(3).is_none());
+        assert_eq!(
+            evaluation.empty_queries() + evaluation.non_empty_queries(),
+            80_000
+        );
+
+        assert!(evaluation.fpr() > 0.0 && evaluation.fpr() < 0.05);
+        assert!(points.fpr() < 0.05);
+        assert_eq!(evaluation.size_in_bits, diva.size_in_bits());
+        assert!(evaluation.bits_per_key() > 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "false negative for key 7")]
+    fn test_false_negative_panics() {
+        let keys = [3, 7, 11];
+        let filter = LeakyFilter::from_keys(&keys, &7);
+        evaluate(&filter, &keys, [(0, 20)]);
+    }
+}
@@ -1,9 +1,17 @@
 use crate::U64_BITS;
-use crate::bitmap::{get_bit, rank, set_bit};
+use crate::bitmap::{clear_bit, get_bit, rank, rank_cached, select, select_cached, set_bit};
 use std::fmt;
-
-const TARGET_SIZE: u16 = 1024;
-// const LOAD_FACTOR: f64 = 0.95;
+use std::mem::size_of;
+
+// quotient bits of a store, the occupieds bitmap holds one bit per quotient
+// 10 bits - 1024 quotients by default
+pub const DEFAULT_QUOTIENT_SIZE: u8 = 10;
+pub const MIN_QUOTIENT_SIZE: u8 = 4;
+pub const MAX_QUOTIENT_SIZE: u8 = 16;
+// load factors kept by the resizing updates: a store grows once an insert would fill
+// more than LOAD_FACTOR of its slots, and shrinks once it fills less than MIN_LOAD_FACTOR
+const LOAD_FACTOR: f64 = 0.95;
+const MIN_LOAD_FACTOR: f64 = 0.75;
 const SIZE_GRADE_COUNT: usize = 31;
 // const DEFAULT_SIZE_GRADE: u8 = 14;
 
@@ -15,61 +23,173 @@ const SCALED_SIZES: [u16; 31] = [
     1258, 1325, 1395, 1469, 1547, 1629, 1715, 1806, 1901, 2002, 2108, 2219, 2326,
 ];
 
+// most infixes a store holds, in its largest size grade
+pub const MAX_ELEMENTS: usize = SCALED_SIZES[SIZE_GRADE_COUNT - 1] as usize;
+
 /// Memory layout of data:
-/// [popcounts: 64 bits] [occupieds: TARGET_SIZE bits]
+/// [popcounts: 64 bits] [occupieds: 2^quotient_size bits]
 /// [runends: num_slots bits] [slots: num_slots * remainder_size bits]
 /// popcounts: 32 bits for occupieds and 32 bits for runends
 #[derive(Debug, Default)]
 pub struct InfixStore {
     elem_count: u16,
     size_grade: u8, // decides the number of slots in the infix store
+    quotient_size: u8,
     remainder_size: u8,
     data: Vec<u64>,
 }
 
+/// Error returned by in-place updates of an InfixStore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfixStoreError {
+    /// the infixes need more slots than the size grade has
+    Full { num_slots: usize },
+    /// there is no such size grade
+    InvalidSizeGrade { size_grade: u8 },
+    /// the quotient of the infix has no bit in the occupieds bitmap
+    QuotientOutOfRange { quotient: u64, num_quotients: usize },
+    /// the infix is not stored
+    NotFound { infix: u64 },
+    /// the data words do not match the size grade, quotient and remainder sizes
+    DataLength { words: usize, expected: usize },
+    /// occupied quotients and runs differ in number
+    RunCountMismatch { occupieds: usize, runends: usize },
+    /// elem_count differs from the slots the runs fill
+    ElemCountMismatch {
+        elem_count: usize,
+        used_slots: usize,
+    },
+    /// the popcounts cached in data[0] differ from the bitmaps
+    StalePopcounts { cached: u64, expected: u64 },
+    /// a remainder is smaller than the one before it in its run
+    UnsortedRun { quotient: u64, slot: usize },
+}
+
+impl fmt::Display for InfixStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Full { num_slots } => write!(f, "infix store is full ({} slots)", num_slots),
+            Self::InvalidSizeGrade { size_grade } => {
+                write!(f, "size grade {} does not exist", size_grade)
+            }
+            Self::QuotientOutOfRange {
+                quotient,
+                num_quotients,
+            } => write!(
+                f,
+                "quotient {} is out of range ({} quotients)",
+                quotient, num_quotients
+            ),
+            Self::NotFound { infix } => write!(f, "infix {} is not stored", infix),
+            Self::DataLength { words, expected } => {
+                write!(f, "{} data words, expected {}", words, expected)
+            }
+            Self::RunCountMismatch { occupieds, runends } => write!(
+                f,
+                "{} occupied quotients but {} runends",
+                occupieds, runends
+            ),
+            Self::ElemCountMismatch {
+                elem_count,
+                used_slots,
+            } => write!(
+                f,
+                "elem_count {} but the runs fill {} slots",
+                elem_count, used_slots
+            ),
+            Self::StalePopcounts { cached, expected } => write!(
+                f,
+                "cached popcounts 0x{:016x}, expected 0x{:016x}",
+                cached, expected
+            ),
+            Self::UnsortedRun { quotient, slot } => write!(
+                f,
+                "run of quotient {} is not sorted at slot {}",
+                quotient, slot
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InfixStoreError {}
+
 impl InfixStore {
-    /// Create a new InfixStore from sorted extracted infixes
+    /// Create a new InfixStore from sorted extracted infixes, with the default quotient size
     ///
     /// # Arguments
     /// * `infixes` - Sorted list of extracted partial keys (quotient|remainder)
     /// * `remainder_size` - Number of bits for remainder part
     pub fn new_with_infixes(infixes: &[u64], remainder_size: u8) -> Self {
+        Self::new_with_quotient_size(infixes, DEFAULT_QUOTIENT_SIZE, remainder_size)
+    }
+
+    /// Create a new InfixStore from sorted extracted infixes
+    ///
+    /// # Arguments
+    /// * `infixes` - Sorted list of extracted partial keys (quotient|remainder)
+    /// * `quotient_size` - Number of bits for quotient part, sizes the occupieds bitmap
+    /// * `remainder_size` - Number of bits for remainder part
+    pub fn new_with_quotient_size(infixes: &[u64], quotient_size: u8, remainder_size: u8) -> Self {
         // step 1: determine size_grade based on number of elements
         let size_grade = Self::choose_size_grade(infixes.len());
         let num_slots = SCALED_SIZES[size_grade as usize];
 
         // step 2: calculate total data size needed
-        // [popcounts: 64 bits] [occupieds: TARGET_SIZE bits]
-        // [runends: num_slots bits] [slots: num_slots * remainder_size bits]
-        let popcounts_words = 1;
-        let occupieds_words = (TARGET_SIZE as usize + U64_BITS - 1) / U64_BITS;
-        let runends_words = (num_slots as usize + U64_BITS - 1) / U64_BITS;
-        let slots_bits = num_slots as usize * remainder_size as usize;
-        let slots_words = (slots_bits + U64_BITS - 1) / U64_BITS;
-
-        let total_words = popcounts_words + occupieds_words + runends_words + slots_words;
+        let total_words = Self::size_in_words(infixes.len(), quotient_size, remainder_size);
         let mut data = vec![0u64; total_words];
 
         if infixes.is_empty() {
             return Self {
                 elem_count: 0,
                 size_grade,
+                quotient_size,
                 remainder_size,
                 data,
             };
         }
 
         // step 3: load infixes in the infix store
-        Self::load_infixes_to_store(&mut data, infixes, remainder_size, num_slots);
+        Self::load_infixes_to_store(&mut data, infixes, quotient_size, remainder_size, num_slots);
 
         Self {
             elem_count: infixes.len() as u16,
             size_grade,
+            quotient_size,
             remainder_size,
             data,
         }
     }
 
+    /// choose the quotient size for a store holding `num_elements` infixes
+    /// one quotient per element keeps the FPR of a store close to 2 / 2^remainder_size
+    /// whether its bucket is dense or sparse
+    pub fn choose_quotient_size(num_elements: usize) -> u8 {
+        let quotient_size = num_elements.max(1).next_power_of_two().trailing_zeros() as u8;
+        quotient_size.clamp(MIN_QUOTIENT_SIZE, MAX_QUOTIENT_SIZE)
+    }
+
+    /// data words of a store holding `num_elements` infixes
+    /// [popcounts: 64 bits] [occupieds: 2^quotient_size bits]
+    /// [runends: num_slots bits] [slots: num_slots * remainder_size bits]
+    pub fn size_in_words(num_elements: usize, quotient_size: u8, remainder_size: u8) -> usize {
+        let size_grade = Self::choose_size_grade(num_elements);
+        Self::size_in_words_for_grade(size_grade, quotient_size, remainder_size)
+    }
+
+    /// data words of a store in a size grade
+    fn size_in_words_for_grade(size_grade: u8, quotient_size: u8, remainder_size: u8) -> usize {
+        let num_slots = SCALED_SIZES[size_grade as usize] as usize;
+        let popcounts_words = 1;
+        let runends_words = num_slots.div_ceil(U64_BITS);
+        let slots_words = (num_slots * remainder_size as usize).div_ceil(U64_BITS);
+        popcounts_words + Self::occupieds_words(quotient_size) + runends_words + slots_words
+    }
+
+    /// number of words of the occupieds bitmap
+    fn occupieds_words(quotient_size: u8) -> usize {
+        (1usize << quotient_size).div_ceil(U64_BITS)
+    }
+
     /// choose appropriate size_grade based on number of elements
     fn choose_size_grade(num_elements: usize) -> u8 {
         for grade in 0..SIZE_GRADE_COUNT {
@@ -80,19 +200,28 @@ impl InfixStore {
         (SIZE_GRADE_COUNT - 1) as u8
     }
 
+    /// size grade the resizing updates keep a store of `num_elements` infixes in:
+    /// the smallest grade filled to at most LOAD_FACTOR, or the largest grade
+    fn fitting_size_grade(num_elements: usize) -> u8 {
+        (0..SIZE_GRADE_COUNT)
+            .find(|&grade| num_elements as f64 <= SCALED_SIZES[grade] as f64 * LOAD_FACTOR)
+            .unwrap_or(SIZE_GRADE_COUNT - 1) as u8
+    }
+
     /// load sorted infixes into the infix store
     fn load_infixes_to_store(
         data: &mut [u64],
         infixes: &[u64],
+        quotient_size: u8,
         remainder_size: u8,
         num_slots: u16,
     ) {
         let occupieds_start = 1;
-        let occupieds_words = (TARGET_SIZE as usize + U64_BITS - 1) / U64_BITS;
+        let occupieds_words = Self::occupieds_words(quotient_size);
         let runends_start = occupieds_start + occupieds_words;
-        let runends_words = (num_slots as usize + U64_BITS - 1) / U64_BITS;
+        let runends_words = (num_slots as usize).div_ceil(U64_BITS);
         let slots_start = runends_start + runends_words;
-        let slots_words = (num_slots as usize * remainder_size as usize + U64_BITS - 1) / U64_BITS;
+        let slots_words = (num_slots as usize * remainder_size as usize).div_ceil(U64_BITS);
 
         let mut slot_pos = 0;
         let mut prev_quotient = None;
@@ -125,7 +254,13 @@ impl InfixStore {
             set_bit(runends_slice, slot_pos - 1);
         }
 
-        Self::compute_popcounts(data, occupieds_start, runends_start, num_slots);
+        Self::compute_popcounts(
+            data,
+            quotient_size,
+            occupieds_start,
+            runends_start,
+            num_slots,
+        );
     }
 
     /// Split infix into quotient and remainder
@@ -160,15 +295,16 @@ impl InfixStore {
     /// Compute and store popcounts for first half. Optimization for rank queries
     fn compute_popcounts(
         data: &mut [u64],
+        quotient_size: u8,
         occupieds_start: usize,
         runends_start: usize,
         num_slots: u16,
     ) {
-        let occupieds_half = TARGET_SIZE as usize / 2;
+        let occupieds_half = (1usize << quotient_size) / 2;
         let runends_half = num_slots as usize / 2;
 
-        let occupieds_words = (TARGET_SIZE as usize + U64_BITS - 1) / U64_BITS;
-        let runends_words = (num_slots as usize + U64_BITS - 1) / U64_BITS;
+        let occupieds_words = Self::occupieds_words(quotient_size);
+        let runends_words = (num_slots as usize).div_ceil(U64_BITS);
 
         let occupieds_slice = &data[occupieds_start..occupieds_start + occupieds_words];
         let runends_slice = &data[runends_start..runends_start + runends_words];
@@ -184,18 +320,32 @@ impl InfixStore {
     fn get_offsets(&self) -> (usize, usize, usize) {
         let num_slots = SCALED_SIZES[self.size_grade as usize];
         let occupieds_start = 1;
-        let occupieds_words = (TARGET_SIZE as usize + U64_BITS - 1) / U64_BITS;
+        let occupieds_words = Self::occupieds_words(self.quotient_size);
         let runends_start = occupieds_start + occupieds_words;
-        let runends_words = (num_slots as usize + U64_BITS - 1) / U64_BITS;
+        let runends_words = (num_slots as usize).div_ceil(U64_BITS);
         let slots_start = runends_start + runends_words;
 
         (occupieds_start, runends_start, slots_start)
     }
 
+    /// occupieds bitmap section of data
+    fn occupieds_slice(&self) -> &[u64] {
+        let (occupieds_start, _, _) = self.get_offsets();
+        let occupieds_words = Self::occupieds_words(self.quotient_size);
+        &self.data[occupieds_start..occupieds_start + occupieds_words]
+    }
+
+    /// runends bitmap section of data
+    fn runends_slice(&self) -> &[u64] {
+        let (_, runends_start, _) = self.get_offsets();
+        let runends_words = self.num_slots().div_ceil(U64_BITS);
+        &self.data[runends_start..runends_start + runends_words]
+    }
+
     /// check if a quotient bit is set in occupieds
     pub fn is_occupied(&self, quotient: usize) -> bool {
         let (occupieds_start, _, _) = self.get_offsets();
-        let occupieds_words = (TARGET_SIZE as usize + U64_BITS - 1) / U64_BITS;
+        let occupieds_words = Self::occupieds_words(self.quotient_size);
         let occupieds_slice = &self.data[occupieds_start..occupieds_start + occupieds_words];
         get_bit(occupieds_slice, quotient)
     }
@@ -204,17 +354,148 @@ impl InfixStore {
     pub fn is_runend(&self, slot_pos: usize) -> bool {
         let num_slots = SCALED_SIZES[self.size_grade as usize];
         let (_, runends_start, _) = self.get_offsets();
-        let runends_words = (num_slots as usize + U64_BITS - 1) / U64_BITS;
+        let runends_words = (num_slots as usize).div_ceil(U64_BITS);
         let runends_slice = &self.data[runends_start..runends_start + runends_words];
         get_bit(runends_slice, slot_pos)
     }
 
+    /// number of occupied quotients strictly below `quotient`
+    pub fn occupieds_rank(&self, quotient: usize) -> usize {
+        let occupieds_popcount = (self.data[0] >> 32) as usize;
+        rank_cached(
+            self.occupieds_slice(),
+            quotient,
+            self.num_quotients() / 2,
+            occupieds_popcount,
+        )
+    }
+
+    /// find the slots holding the run of a quotient
+    /// returns: (first_slot, last_slot) inclusive, or None if the quotient is not occupied
+    pub fn run_bounds(&self, quotient: usize) -> Option<(usize, usize)> {
+        if !self.is_occupied(quotient) {
+            return None;
+        }
+        self.run_slots(self.occupieds_rank(quotient))
+    }
+
+    /// slots holding the `run_index`-th run
+    /// runs are stored back to back in quotient order, so the i-th occupied
+    /// quotient owns the slots after the (i-1)-th runend up to the i-th runend
+    fn run_slots(&self, run_index: usize) -> Option<(usize, usize)> {
+        let runends_slice = self.runends_slice();
+        let half_slots = self.num_slots() / 2;
+        let runends_popcount = (self.data[0] & 0xFFFFFFFF) as usize;
+        let runend = |rank| select_cached(runends_slice, rank, half_slots, runends_popcount);
+
+        let last_slot = runend(run_index)?;
+        let first_slot = match run_index {
+            0 => 0,
+            _ => runend(run_index - 1)? + 1,
+        };
+        Some((first_slot, last_slot))
+    }
+
+    /// Range of full-length infixes covered by a stored infix
+    ///
+    /// The lowest set bit of an infix terminates it: an infix whose terminator sits
+    /// higher stands for every infix sharing the bits above it.
+    /// returns: (lowest_infix, highest_infix) inclusive
+    pub fn covered_infixes(infix: u64, remainder_size: u8) -> (u64, u64) {
+        // the terminator never sits above the remainder, so a stored infix stays in its quotient
+        let terminator = infix.trailing_zeros().min(remainder_size as u32 - 1);
+        let half_width = (1 << terminator) - 1;
+        (infix - half_width, infix + half_width)
+    }
+
+    /// check whether a stored infix covers a full-length infix
+    pub fn contains_infix(&self, infix: u64) -> bool {
+        // compare against the remainders in the run of the infix's quotient
+        let quotient = (infix >> self.remainder_size) as usize;
+        let Some((first_slot, last_slot)) = self.run_bounds(quotient) else {
+            return false;
+        };
+
+        (first_slot..=last_slot).any(|slot| {
+            let stored = ((quotient as u64) << self.remainder_size) | self.read_slot(slot);
+            let (lowest, highest) = Self::covered_infixes(stored, self.remainder_size);
+            lowest <= infix && infix <= highest
+        })
+    }
+
+    /// Check whether any stored infix covers part of [lo_infix, hi_infix]
+    ///
+    /// Empty quotients are skipped in bulk: the cached ranks of the occupieds bitmap
+    /// count the runs in the range, and any run strictly inside it answers the query.
+    /// Remainders are compared only in the runs of the two boundary quotients.
+    pub fn any_in_range(&self, lo_infix: u64, hi_infix: u64) -> bool {
+        let lo_quotient = lo_infix >> self.remainder_size;
+        if lo_infix > hi_infix || lo_quotient >= self.num_quotients() as u64 {
+            return false;
+        }
+        let lo_quotient = lo_quotient as usize;
+        let hi_quotient =
+            ((hi_infix >> self.remainder_size) as usize).min(self.num_quotients() - 1);
+
+        // runs of the occupied quotients in [lo_quotient, hi_quotient]
+        let first_run = self.occupieds_rank(lo_quotient);
+        let end_run = self.occupieds_rank(hi_quotient + 1);
+        let lo_occupied = self.is_occupied(lo_quotient);
+        let hi_occupied = hi_quotient > lo_quotient && self.is_occupied(hi_quotient);
+        if end_run - first_run > lo_occupied as usize + hi_occupied as usize {
+            return true;
+        }
+
+        // the boundary quotients need their remainders compared
+        let boundary_runs = [
+            lo_occupied.then_some((lo_quotient, first_run)),
+            hi_occupied.then(|| (hi_quotient, end_run - 1)),
+        ];
+        boundary_runs
+            .into_iter()
+            .flatten()
+            .any(|(quotient, run_index)| {
+                let Some((first_slot, last_slot)) = self.run_slots(run_index) else {
+                    return false;
+                };
+                (first_slot..=last_slot).any(|slot| {
+                    let infix = ((quotient as u64) << self.remainder_size) | self.read_slot(slot);
+                    let (lowest, highest) = Self::covered_infixes(infix, self.remainder_size);
+                    lowest <= hi_infix && highest >= lo_infix
+                })
+            })
+    }
+
+    /// Iterate over the stored (quotient, remainder) pairs in sorted order
+    ///
+    /// Walks occupieds and runends together: each set occupieds bit owns the slots up
+    /// to the next runend, and empty occupieds words are skipped whole.
+    pub fn iter(&self) -> InfixStoreIter<'_> {
+        let occupieds = self.occupieds_slice();
+        InfixStoreIter {
+            store: self,
+            occupieds,
+            word_index: 0,
+            word: occupieds[0],
+            quotient: 0,
+            slot: 0,
+            in_run: false,
+        }
+    }
+
+    /// every stored infix (quotient|remainder) in sorted order, as taken by
+    /// `new_with_infixes` and `new_with_quotient_size`
+    pub fn to_infixes(&self) -> Vec<u64> {
+        self.iter()
+            .map(|(quotient, remainder)| (quotient << self.remainder_size) | remainder)
+            .collect()
+    }
+
     /// read remainder value from a specific slot
     pub fn read_slot(&self, slot_index: usize) -> u64 {
         let num_slots = SCALED_SIZES[self.size_grade as usize];
         let (_, _, slots_start) = self.get_offsets();
-        let slots_words =
-            (num_slots as usize * self.remainder_size as usize + U64_BITS - 1) / U64_BITS;
+        let slots_words = (num_slots as usize * self.remainder_size as usize).div_ceil(U64_BITS);
         let slots_slice = &self.data[slots_start..slots_start + slots_words];
 
         let bit_pos = slot_index * self.remainder_size as usize;
@@ -235,6 +516,237 @@ impl InfixStore {
         result
     }
 
+    /// Insert an infix (quotient|remainder) in place, keeping its run sorted
+    ///
+    /// The run of the quotient is found with rank/select over occupieds and runends,
+    /// later slots shift up by one, keeping their runend bits. Fails if every slot of
+    /// the current size grade is used, the store does not grow on its own.
+    pub fn insert(&mut self, infix: u64) -> Result<(), InfixStoreError> {
+        let (quotient, remainder) = Self::split_infix(infix, self.remainder_size);
+        let quotient = self.checked_quotient(quotient)?;
+        if self.elem_count() == self.num_slots() {
+            return Err(InfixStoreError::Full {
+                num_slots: self.num_slots(),
+            });
+        }
+
+        let (slot_pos, extends_run) = match self.run_bounds(quotient) {
+            Some((first_slot, last_slot)) => {
+                let slot_pos = (first_slot..=last_slot)
+                    .find(|&slot| self.read_slot(slot) > remainder)
+                    .unwrap_or(last_slot + 1);
+                (slot_pos, slot_pos == last_slot + 1)
+            }
+            // a new run starts right after the run of the previous occupied quotient
+            None => {
+                let run_index = self.occupieds_rank(quotient);
+                let slot_pos = match run_index {
+                    0 => 0,
+                    _ => self
+                        .run_slots(run_index - 1)
+                        .map_or(0, |(_, last_slot)| last_slot + 1),
+                };
+                (slot_pos, false)
+            }
+        };
+
+        // shift the following slots and their runend bits up by one
+        for slot in (slot_pos..self.elem_count()).rev() {
+            let remainder = self.read_slot(slot);
+            let is_runend = self.is_runend(slot);
+            self.write_slot_at(slot + 1, remainder);
+            self.set_runend(slot + 1, is_runend);
+        }
+        self.write_slot_at(slot_pos, remainder);
+
+        if !self.is_occupied(quotient) {
+            // a run of its own
+            self.set_runend(slot_pos, true);
+            self.set_occupied(quotient, true);
+        } else if extends_run {
+            // the run now ends one slot later
+            self.set_runend(slot_pos - 1, false);
+            self.set_runend(slot_pos, true);
+        } else {
+            self.set_runend(slot_pos, false);
+        }
+
+        self.elem_count += 1;
+        self.update_popcounts();
+        Ok(())
+    }
+
+    /// Remove one occurrence of an infix (quotient|remainder)
+    ///
+    /// Later slots shift down by one, keeping their runend bits, and the occupieds
+    /// and runends of the affected run are fixed up. Removing never needs a free
+    /// slot, so unlike `insert` it only fails if the infix is not stored.
+    pub fn remove(&mut self, infix: u64) -> Result<(), InfixStoreError> {
+        let (quotient, remainder) = Self::split_infix(infix, self.remainder_size);
+        let quotient = self.checked_quotient(quotient)?;
+
+        let not_found = InfixStoreError::NotFound { infix };
+        let (first_slot, last_slot) = self.run_bounds(quotient).ok_or(not_found)?;
+        let slot_pos = (first_slot..=last_slot)
+            .find(|&slot| self.read_slot(slot) == remainder)
+            .ok_or(not_found)?;
+
+        // shift the following slots and their runend bits down by one
+        let last_used = self.elem_count as usize - 1;
+        for slot in slot_pos..last_used {
+            let next_remainder = self.read_slot(slot + 1);
+            let next_is_runend = self.is_runend(slot + 1);
+            self.write_slot_at(slot, next_remainder);
+            self.set_runend(slot, next_is_runend);
+        }
+        self.write_slot_at(last_used, 0);
+        self.set_runend(last_used, false);
+
+        if first_slot == last_slot {
+            // the run is gone, so is its quotient
+            self.set_occupied(quotient, false);
+        } else if slot_pos == last_slot {
+            // the run now ends one slot earlier
+            self.set_runend(slot_pos - 1, true);
+        }
+
+        self.elem_count -= 1;
+        self.update_popcounts();
+        Ok(())
+    }
+
+    /// Insert an infix, first moving up size grades if the store would be filled
+    /// to more than LOAD_FACTOR
+    ///
+    /// Fails with `Full` only once the largest size grade is full.
+    pub fn insert_resizing(&mut self, infix: u64) -> Result<(), InfixStoreError> {
+        self.checked_quotient(infix >> self.remainder_size)?;
+        let size_grade = Self::fitting_size_grade(self.elem_count() + 1);
+        if size_grade > self.size_grade {
+            self.resize_to_grade(size_grade)?;
+        }
+        self.insert(infix)
+    }
+
+    /// Remove an infix, then move down size grades if the store is filled to less
+    /// than MIN_LOAD_FACTOR
+    pub fn remove_resizing(&mut self, infix: u64) -> Result<(), InfixStoreError> {
+        self.remove(infix)?;
+        if self.load_factor() < MIN_LOAD_FACTOR {
+            self.resize_to_grade(Self::fitting_size_grade(self.elem_count()))?;
+        }
+        Ok(())
+    }
+
+    /// Move the store to another size grade, keeping its infixes
+    ///
+    /// Occupieds are kept as they are, the runends and slots sections are rebuilt for
+    /// the slot count of the new grade. Fails if the infixes do not fit in it.
+    pub fn resize_to_grade(&mut self, size_grade: u8) -> Result<(), InfixStoreError> {
+        if size_grade as usize >= SIZE_GRADE_COUNT {
+            return Err(InfixStoreError::InvalidSizeGrade { size_grade });
+        }
+        let num_slots = SCALED_SIZES[size_grade as usize] as usize;
+        if self.elem_count() > num_slots {
+            return Err(InfixStoreError::Full { num_slots });
+        }
+        if size_grade == self.size_grade {
+            return Ok(());
+        }
+
+        let mut resized = Self {
+            elem_count: self.elem_count,
+            size_grade,
+            quotient_size: self.quotient_size,
+            remainder_size: self.remainder_size,
+            data: vec![
+                0;
+                Self::size_in_words_for_grade(
+                    size_grade,
+                    self.quotient_size,
+                    self.remainder_size
+                )
+            ],
+        };
+
+        // occupieds do not depend on the slot count, runs stay packed from slot 0
+        let occupieds_words = Self::occupieds_words(self.quotient_size);
+        resized.data[1..1 + occupieds_words].copy_from_slice(self.occupieds_slice());
+        for slot in 0..self.elem_count() {
+            resized.write_slot_at(slot, self.read_slot(slot));
+            resized.set_runend(slot, self.is_runend(slot));
+        }
+
+        resized.update_popcounts();
+        *self = resized;
+        Ok(())
+    }
+
+    /// fraction of the slots holding an infix
+    pub fn load_factor(&self) -> f64 {
+        self.elem_count() as f64 / self.num_slots() as f64
+    }
+
+    /// the quotient as an index into occupieds, if the store has a bit for it
+    fn checked_quotient(&self, quotient: u64) -> Result<usize, InfixStoreError> {
+        if quotient >= self.num_quotients() as u64 {
+            return Err(InfixStoreError::QuotientOutOfRange {
+                quotient,
+                num_quotients: self.num_quotients(),
+            });
+        }
+        Ok(quotient as usize)
+    }
+
+    /// recompute the cached popcounts in data[0] after the bitmaps changed
+    fn update_popcounts(&mut self) {
+        let (occupieds_start, runends_start, _) = self.get_offsets();
+        let num_slots = SCALED_SIZES[self.size_grade as usize];
+        Self::compute_popcounts(
+            &mut self.data,
+            self.quotient_size,
+            occupieds_start,
+            runends_start,
+            num_slots,
+        );
+    }
+
+    /// set or clear the occupieds bit of a quotient
+    fn set_occupied(&mut self, quotient: usize, is_occupied: bool) {
+        let (occupieds_start, _, _) = self.get_offsets();
+        let occupieds_words = Self::occupieds_words(self.quotient_size);
+        let occupieds_slice = &mut self.data[occupieds_start..occupieds_start + occupieds_words];
+        if is_occupied {
+            set_bit(occupieds_slice, quotient);
+        } else {
+            clear_bit(occupieds_slice, quotient);
+        }
+    }
+
+    /// write a remainder value to a specific slot of this store
+    fn write_slot_at(&mut self, slot_index: usize, remainder: u64) {
+        let (_, _, slots_start) = self.get_offsets();
+        let remainder_size = self.remainder_size;
+        Self::write_slot(
+            &mut self.data[slots_start..],
+            slot_index,
+            remainder,
+            remainder_size,
+        );
+    }
+
+    /// set or clear the runend bit of a slot
+    fn set_runend(&mut self, slot_pos: usize, is_runend: bool) {
+        let (_, runends_start, _) = self.get_offsets();
+        let runends_words = self.num_slots().div_ceil(U64_BITS);
+        let runends_slice = &mut self.data[runends_start..runends_start + runends_words];
+        if is_runend {
+            set_bit(runends_slice, slot_pos);
+        } else {
+            clear_bit(runends_slice, slot_pos);
+        }
+    }
+
     pub fn elem_count(&self) -> usize {
         self.elem_count as usize
     }
@@ -243,6 +755,14 @@ impl InfixStore {
         self.size_grade
     }
 
+    pub fn quotient_size(&self) -> u8 {
+        self.quotient_size
+    }
+
+    pub fn num_quotients(&self) -> usize {
+        1 << self.quotient_size
+    }
+
     pub fn remainder_size(&self) -> u8 {
         self.remainder_size
     }
@@ -251,11 +771,176 @@ impl InfixStore {
         SCALED_SIZES[self.size_grade as usize] as usize
     }
 
+    /// bytes used by the store, its fields plus the allocated data words
+    pub fn size_in_bytes(&self) -> usize {
+        size_of::<Self>() + self.data.capacity() * size_of::<u64>()
+    }
+
+    /// raw data words, laid out as described on the struct
+    pub fn data(&self) -> &[u64] {
+        &self.data
+    }
+
+    /// Rebuild an InfixStore from its raw parts, e.g. when deserializing
+    ///
+    /// Returns None if the parts do not describe a consistent store, see `validate`.
+    ///
+    /// # Arguments
+    /// * `elem_count` - Number of stored infixes
+    /// * `size_grade` - Size grade deciding the number of slots
+    /// * `quotient_size` - Number of bits for quotient part
+    /// * `remainder_size` - Number of bits for remainder part
+    /// * `data` - Raw data words
+    pub fn from_raw_parts(
+        elem_count: usize,
+        size_grade: u8,
+        quotient_size: u8,
+        remainder_size: u8,
+        data: Vec<u64>,
+    ) -> Option<Self> {
+        if size_grade as usize >= SIZE_GRADE_COUNT
+            || !(MIN_QUOTIENT_SIZE..=MAX_QUOTIENT_SIZE).contains(&quotient_size)
+            || !(1..U64_BITS as u8).contains(&remainder_size)
+            || elem_count > SCALED_SIZES[size_grade as usize] as usize
+        {
+            return None;
+        }
+
+        let infix_store = Self {
+            elem_count: elem_count as u16,
+            size_grade,
+            quotient_size,
+            remainder_size,
+            data,
+        };
+        infix_store.validate().ok()?;
+        Some(infix_store)
+    }
+
+    /// Check the invariants of the store
+    ///
+    /// The data words must match the layout, every occupied quotient must own exactly
+    /// one run, the runs must fill exactly `elem_count` slots, the popcounts cached in
+    /// data[0] must match the bitmaps and the remainders of each run must be sorted.
+    pub fn validate(&self) -> Result<(), InfixStoreError> {
+        let expected =
+            Self::size_in_words_for_grade(self.size_grade, self.quotient_size, self.remainder_size);
+        if self.data.len() != expected {
+            return Err(InfixStoreError::DataLength {
+                words: self.data.len(),
+                expected,
+            });
+        }
+
+        // every occupied quotient owns one run
+        let count_ones =
+            |words: &[u64]| -> usize { words.iter().map(|w| w.count_ones() as usize).sum() };
+        let occupieds = count_ones(self.occupieds_slice());
+        let runends = count_ones(self.runends_slice());
+        if occupieds != runends {
+            return Err(InfixStoreError::RunCountMismatch { occupieds, runends });
+        }
+
+        // the runs fill the slots up to the last runend
+        let used_slots = runends
+            .checked_sub(1)
+            .and_then(|rank| select(self.runends_slice(), rank))
+            .map_or(0, |last_runend| last_runend + 1);
+        if used_slots != self.elem_count() {
+            return Err(InfixStoreError::ElemCountMismatch {
+                elem_count: self.elem_count(),
+                used_slots,
+            });
+        }
+
+        let mut recomputed = self.data.clone();
+        let (occupieds_start, runends_start, _) = self.get_offsets();
+        Self::compute_popcounts(
+            &mut recomputed,
+            self.quotient_size,
+            occupieds_start,
+            runends_start,
+            SCALED_SIZES[self.size_grade as usize],
+        );
+        if recomputed[0] != self.data[0] {
+            return Err(InfixStoreError::StalePopcounts {
+                cached: self.data[0],
+                expected: recomputed[0],
+            });
+        }
+
+        let mut previous = None;
+        for (slot, (quotient, remainder)) in self.iter().enumerate() {
+            if let Some((previous_quotient, previous_remainder)) = previous
+                && previous_quotient == quotient
+                && previous_remainder > remainder
+            {
+                return Err(InfixStoreError::UnsortedRun { quotient, slot });
+            }
+            previous = Some((quotient, remainder));
+        }
+
+        Ok(())
+    }
+
     pub fn pretty_print(&self) {
         print!("{}", self);
     }
 }
 
+/// Iterator over the (quotient, remainder) pairs of an InfixStore, see `InfixStore::iter`
+pub struct InfixStoreIter<'a> {
+    store: &'a InfixStore,
+    occupieds: &'a [u64],
+    word_index: usize,
+    word: u64, // occupieds bits of the current word not visited yet
+    quotient: u64,
+    slot: usize,
+    in_run: bool,
+}
+
+impl Iterator for InfixStoreIter<'_> {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        if self.slot >= self.store.elem_count() {
+            return None;
+        }
+
+        if !self.in_run {
+            // the next occupied quotient owns the run starting at this slot
+            while self.word == 0 {
+                self.word_index += 1;
+                self.word = *self.occupieds.get(self.word_index)?;
+            }
+            self.quotient = (self.word_index * U64_BITS) as u64 + self.word.trailing_zeros() as u64;
+            self.word &= self.word - 1;
+            self.in_run = true;
+        }
+
+        let remainder = self.store.read_slot(self.slot);
+        self.in_run = !self.store.is_runend(self.slot);
+        self.slot += 1;
+        Some((self.quotient, remainder))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.store.elem_count() - self.slot;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for InfixStoreIter<'_> {}
+
+impl<'a> IntoIterator for &'a InfixStore {
+    type Item = (u64, u64);
+    type IntoIter = InfixStoreIter<'a>;
+
+    fn into_iter(self) -> InfixStoreIter<'a> {
+        self.iter()
+    }
+}
+
 impl fmt::Display for InfixStore {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let num_slots = SCALED_SIZES[self.size_grade as usize];
@@ -264,23 +949,20 @@ impl fmt::Display for InfixStore {
         writeln!(f, "elem_count: {}", self.elem_count)?;
         writeln!(f, "size_grade: {}", self.size_grade)?;
         writeln!(f, "num_slots: {}", num_slots)?;
+        writeln!(f, "quotient_size: {} bits", self.quotient_size)?;
         writeln!(f, "remainder_size: {} bits", self.remainder_size)?;
         writeln!(f)?;
 
         writeln!(f, "popcounts: 0x{:016x}", self.data[0])?;
-        let occupieds_popcount = (self.data[0] & 0xFFFFFFFF) as u32;
-        let runends_popcount = (self.data[0] >> 32) as u32;
+        let occupieds_popcount = (self.data[0] >> 32) as u32;
+        let runends_popcount = (self.data[0] & 0xFFFFFFFF) as u32;
         writeln!(f, "  occupieds_popcount: {}", occupieds_popcount)?;
         writeln!(f, "  runends_popcount: {}", runends_popcount)?;
         writeln!(f)?;
 
         writeln!(f, "occupieds bitmap (showing set quotients):")?;
-        let mut occupied_quotients = Vec::new();
-        for q in 0..TARGET_SIZE as usize {
-            if self.is_occupied(q) {
-                occupied_quotients.push(q);
-            }
-        }
+        let mut occupied_quotients: Vec<u64> = self.iter().map(|(quotient, _)| quotient).collect();
+        occupied_quotients.dedup();
         if occupied_quotients.is_empty() {
             writeln!(f, "  (none)")?;
         } else {
@@ -338,6 +1020,8 @@ impl fmt::Display for InfixStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data_gen::DataGen;
+    use rand::Rng;
 
     #[test]
     fn test_split_infix() {
@@ -371,7 +1055,7 @@ mod tests {
         let store = InfixStore::new_with_infixes(&infixes, 8);
 
         assert_eq!(store.elem_count, 5);
-        // assert_eq!(store.quotient_size, 10);
+        assert_eq!(store.quotient_size, 10);
         assert_eq!(store.remainder_size, 8);
 
         // verify occupieds: quotients 129 and 340 should be set
@@ -473,4 +1157,443 @@ mod tests {
             assert_eq!(store.read_slot(1), max_remainder - 1);
         }
     }
+
+    #[test]
+    fn test_to_infixes() {
+        let infixes = vec![
+            (3u64 << 8) | 1,
+            (3u64 << 8) | 7,
+            (129u64 << 8) | 170,
+            (340u64 << 8) | 51,
+            (340u64 << 8) | 51,
+            (1023u64 << 8) | 255,
+        ];
+
+        let store = InfixStore::new_with_infixes(&infixes, 8);
+        assert_eq!(store.to_infixes(), infixes);
+
+        let empty = InfixStore::new_with_infixes(&[], 8);
+        assert!(empty.to_infixes().is_empty());
+    }
+
+    #[test]
+    fn test_iter() {
+        let infixes = vec![(3u64 << 8) | 1, (3u64 << 8) | 7, (900u64 << 8) | 42];
+        let store = InfixStore::new_with_infixes(&infixes, 8);
+        let mut iter = store.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some((3, 1)));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(3, 7), (900, 42)]);
+        assert_eq!((&store).into_iter().count(), 3);
+        assert_eq!(InfixStore::new_with_infixes(&[], 8).iter().next(), None);
+
+        // round trip through bulk construction, sparse and dense occupieds alike
+        let mut data_gen = DataGen::new(29);
+        for (quotient_size, num_infixes) in [(4, 300), (12, 50), (16, 2000)] {
+            let max_infix = (1u64 << (quotient_size + 8)) - 1;
+            let mut infixes = data_gen.uniform_u64(num_infixes, 0, max_infix);
+            infixes.sort();
+            let store = InfixStore::new_with_quotient_size(&infixes, quotient_size, 8);
+            assert_eq!(store.to_infixes(), infixes);
+
+            let rebuilt = InfixStore::new_with_quotient_size(&store.to_infixes(), quotient_size, 8);
+            assert_eq!(rebuilt.data(), store.data());
+            assert!(
+                store
+                    .iter()
+                    .all(|(quotient, _)| store.is_occupied(quotient as usize))
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let infixes = vec![
+            (129u64 << 8) | 170,
+            (129u64 << 8) | 188,
+            (129u64 << 8) | 207,
+            (200u64 << 8) | 5,
+            (340u64 << 8) | 51,
+            (340u64 << 8) | 90,
+        ];
+        let mut store = InfixStore::new_with_infixes(&infixes, 8);
+
+        // not stored
+        let infix = (129u64 << 8) | 171;
+        assert_eq!(
+            store.remove(infix),
+            Err(InfixStoreError::NotFound { infix })
+        );
+        assert!(store.remove((130u64 << 8) | 170).is_err());
+        assert_eq!(store.elem_count(), 6);
+
+        // middle of a run
+        assert!(store.remove((129u64 << 8) | 188).is_ok());
+        assert_eq!(store.elem_count(), 5);
+        assert!(store.is_occupied(129));
+        assert!(!store.is_runend(0));
+        assert!(store.is_runend(1));
+        assert_eq!(store.read_slot(1), 207);
+
+        // a single-element run clears its quotient
+        assert!(store.remove((200u64 << 8) | 5).is_ok());
+        assert!(!store.is_occupied(200));
+        assert_eq!(store.read_slot(2), 51);
+
+        // end of a run moves the runend back
+        assert!(store.remove((340u64 << 8) | 90).is_ok());
+        assert!(store.is_runend(2));
+        assert!(!store.is_runend(3));
+
+        assert_eq!(
+            store.to_infixes(),
+            vec![(129u64 << 8) | 170, (129u64 << 8) | 207, (340u64 << 8) | 51]
+        );
+    }
+
+    #[test]
+    fn test_remove_duplicates() {
+        let infix = (77u64 << 8) | 9;
+        let mut store = InfixStore::new_with_infixes(&[infix, infix], 8);
+
+        assert!(store.remove(infix).is_ok());
+        assert!(store.is_occupied(77));
+        assert!(store.is_runend(0));
+
+        assert!(store.remove(infix).is_ok());
+        assert!(!store.is_occupied(77));
+        assert_eq!(store.elem_count(), 0);
+        assert_eq!(
+            store.remove(infix),
+            Err(InfixStoreError::NotFound { infix })
+        );
+    }
+
+    #[test]
+    fn test_insert() {
+        let infixes = vec![
+            (3u64 << 8) | 7,
+            (129u64 << 8) | 188,
+            (3u64 << 8) | 1,
+            (340u64 << 8) | 51,
+            (129u64 << 8) | 170,
+            (129u64 << 8) | 207,
+            (340u64 << 8) | 51,
+            0,
+            (1023u64 << 8) | 255,
+        ];
+        let mut store = InfixStore::new_with_infixes(&[], 8);
+        for &infix in &infixes {
+            store.insert(infix).unwrap();
+        }
+
+        // same bits as a store built in bulk, popcounts included
+        let mut sorted = infixes.clone();
+        sorted.sort();
+        let built = InfixStore::new_with_infixes(&sorted, 8);
+        assert_eq!(store.elem_count(), infixes.len());
+        assert_eq!(store.data(), built.data());
+        assert_eq!(store.to_infixes(), sorted);
+
+        // inserts and removes in any order match the bulk construction
+        for &infix in &infixes[..4] {
+            store.remove(infix).unwrap();
+        }
+        store.insert((500u64 << 8) | 3).unwrap();
+        let mut remaining = infixes[4..].to_vec();
+        remaining.push((500u64 << 8) | 3);
+        remaining.sort();
+        assert_eq!(
+            store.data(),
+            InfixStore::new_with_infixes(&remaining, 8).data()
+        );
+
+        let infix = 1024u64 << 8;
+        assert_eq!(
+            store.insert(infix),
+            Err(InfixStoreError::QuotientOutOfRange {
+                quotient: 1024,
+                num_quotients: 1024
+            })
+        );
+    }
+
+    #[test]
+    fn test_insert_full() {
+        let mut store = InfixStore::new_with_quotient_size(&[], 4, 8);
+        let num_slots = store.num_slots();
+        for i in 0..num_slots as u64 {
+            store.insert(((i * 7 % 16) << 8) | (i % 256)).unwrap();
+        }
+
+        let error = store.insert(5 << 8).unwrap_err();
+        assert_eq!(error, InfixStoreError::Full { num_slots });
+        assert_eq!(store.elem_count(), num_slots);
+        assert_eq!(store.num_slots(), num_slots);
+
+        // a removal frees a slot again
+        store.remove(7 << 8 | 1).unwrap();
+        store.insert(5 << 8).unwrap();
+        assert!(store.contains_infix(5 << 8));
+        assert!(store.is_runend(num_slots - 1));
+    }
+
+    #[test]
+    fn test_resize_to_grade() {
+        let infixes: Vec<u64> = (0..400u64).map(|i| ((i * 2) << 8) | (i % 256)).collect();
+        let mut store = InfixStore::new_with_infixes(&infixes, 8);
+        assert_eq!(store.size_grade(), 0);
+
+        // through every grade and back, the store matches one built in the grade
+        for size_grade in [30, 14, 3, 0] {
+            store.resize_to_grade(size_grade).unwrap();
+            assert_eq!(store.size_grade(), size_grade);
+            assert_eq!(
+                store.num_slots(),
+                SCALED_SIZES[size_grade as usize] as usize
+            );
+            assert_eq!(store.to_infixes(), infixes);
+
+            let mut built = InfixStore::new_with_infixes(&[], 8);
+            built.resize_to_grade(size_grade).unwrap();
+            infixes
+                .iter()
+                .for_each(|&infix| built.insert(infix).unwrap());
+            assert_eq!(store.data(), built.data());
+        }
+
+        assert_eq!(
+            store.resize_to_grade(31),
+            Err(InfixStoreError::InvalidSizeGrade { size_grade: 31 })
+        );
+        let mut full = InfixStore::new_with_infixes(&infixes[..300], 8);
+        full.resize_to_grade(30).unwrap();
+        infixes[300..]
+            .iter()
+            .chain(&infixes[..200])
+            .for_each(|&infix| {
+                full.insert(infix).unwrap();
+            });
+        assert_eq!(
+            full.resize_to_grade(0),
+            Err(InfixStoreError::Full { num_slots: 463 })
+        );
+        assert_eq!(full.size_grade(), 30);
+    }
+
+    #[test]
+    fn test_resizing_updates() {
+        let infixes: Vec<u64> = (0..2000u64)
+            .map(|i| ((i * 7 % 1024) << 8) | (i % 256) | 1)
+            .collect();
+        let mut store = InfixStore::new_with_infixes(&[], 8);
+        for (i, &infix) in infixes.iter().enumerate() {
+            store.insert_resizing(infix).unwrap();
+            assert_eq!(store.elem_count(), i + 1);
+            assert!(store.load_factor() <= LOAD_FACTOR);
+        }
+        assert!(store.num_slots() >= 2000);
+        assert!(infixes.iter().all(|&infix| store.contains_infix(infix)));
+
+        // shrinking keeps the load between the two thresholds
+        for (i, &infix) in infixes.iter().enumerate().skip(100) {
+            store.remove_resizing(infix).unwrap();
+            if store.size_grade() > 0 {
+                assert!(
+                    store.load_factor() >= MIN_LOAD_FACTOR,
+                    "after {} removals",
+                    i
+                );
+            }
+        }
+        assert_eq!(store.size_grade(), 0);
+        let mut remaining = infixes[..100].to_vec();
+        remaining.sort();
+        assert_eq!(store.to_infixes(), remaining);
+    }
+
+    #[test]
+    fn test_any_in_range() {
+        let infixes = vec![
+            (3u64 << 8) | 0b1000_0000,
+            (129u64 << 8) | 170,
+            (129u64 << 8) | 207,
+            (340u64 << 8) | 51,
+            (1023u64 << 8) | 255,
+        ];
+        let store = InfixStore::new_with_infixes(&infixes, 8);
+
+        // a run strictly inside the range, found without reading remainders
+        assert!(store.any_in_range(4 << 8, 1000 << 8));
+        assert!(!store.any_in_range(130 << 8, (340 << 8) | 50));
+        assert!(store.any_in_range(130 << 8, (340 << 8) | 51));
+        assert!(!store.any_in_range((129 << 8) | 172, (129 << 8) | 206));
+        assert!(store.any_in_range((129 << 8) | 172, (129 << 8) | 207));
+        // an infix with a high terminator covers its neighbours
+        assert!(store.any_in_range((3 << 8) | 1, (3 << 8) | 2));
+        assert!(!store.any_in_range(0, 2 << 8));
+        assert!(!store.any_in_range(5, 4));
+        assert!(store.any_in_range(1023 << 8, u64::MAX));
+
+        // agrees with a scan of the decoded infixes, for every quotient size and
+        // size grade, whose cached halfway points are rarely word aligned
+        let mut rng = DataGen::new(23);
+        for (quotient_size, num_infixes) in [(4, 40), (6, 300), (10, 700), (12, 2000)] {
+            let max_infix = (1u64 << (quotient_size + 8)) - 1;
+            let mut infixes: Vec<u64> = rng
+                .uniform_u64(num_infixes, 0, max_infix)
+                .into_iter()
+                .map(|infix| infix | 1)
+                .collect();
+            infixes.sort();
+            let store = InfixStore::new_with_quotient_size(&infixes, quotient_size, 8);
+
+            let bounds = rng.uniform_u64(2000, 0, max_infix);
+            for pair in bounds.chunks(2) {
+                let (lo, hi) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+                let hi = hi.min(lo + (max_infix >> rng.rng().gen_range(0..16)));
+                let expected = infixes.iter().any(|&infix| {
+                    let (lowest, highest) = InfixStore::covered_infixes(infix, 8);
+                    lowest <= hi && highest >= lo
+                });
+                assert_eq!(store.any_in_range(lo, hi), expected, "[{}, {}]", lo, hi);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_raw_parts() {
+        let infixes = vec![(3u64 << 8) | 1, (3u64 << 8) | 7, (900u64 << 8) | 42];
+        let store = InfixStore::new_with_infixes(&infixes, 8);
+        let data = store.data().to_vec();
+
+        let rebuilt =
+            InfixStore::from_raw_parts(3, store.size_grade(), 10, 8, data.clone()).unwrap();
+        assert_eq!(rebuilt.to_infixes(), infixes);
+
+        let empty = InfixStore::new_with_infixes(&[], 8);
+        let rebuilt =
+            InfixStore::from_raw_parts(0, empty.size_grade(), 10, 8, empty.data().to_vec());
+        assert_eq!(rebuilt.unwrap().elem_count(), 0);
+
+        // inconsistent parts are rejected
+        let grade = store.size_grade();
+        assert!(InfixStore::from_raw_parts(2, grade, 10, 8, data.clone()).is_none());
+        assert!(InfixStore::from_raw_parts(3, 31, 10, 8, data.clone()).is_none());
+        assert!(InfixStore::from_raw_parts(3, grade, 10, 9, data.clone()).is_none());
+        assert!(InfixStore::from_raw_parts(3, grade, 11, 8, data.clone()).is_none());
+        assert!(InfixStore::from_raw_parts(3, grade, 10, 8, data[1..].to_vec()).is_none());
+
+        let mut corrupted = data.clone();
+        corrupted[1] ^= 1;
+        assert!(InfixStore::from_raw_parts(3, grade, 10, 8, corrupted).is_none());
+
+        let mut corrupted = data;
+        corrupted[0] ^= 1 << 32;
+        assert!(InfixStore::from_raw_parts(3, grade, 10, 8, corrupted).is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let infixes = vec![
+            (3u64 << 8) | 1,
+            (3u64 << 8) | 7,
+            (129u64 << 8) | 170,
+            (129u64 << 8) | 188,
+            (340u64 << 8) | 51,
+        ];
+        let store = InfixStore::new_with_infixes(&infixes, 8);
+        assert_eq!(store.validate(), Ok(()));
+        assert_eq!(InfixStore::new_with_infixes(&[], 8).validate(), Ok(()));
+        let corrupt = |change: &dyn Fn(&mut InfixStore)| {
+            let mut corrupted = InfixStore::new_with_infixes(&infixes, 8);
+            change(&mut corrupted);
+            corrupted.validate().unwrap_err()
+        };
+
+        // an occupied quotient without a run
+        assert_eq!(
+            corrupt(&|store| store.set_occupied(500, true)),
+            InfixStoreError::RunCountMismatch {
+                occupieds: 4,
+                runends: 3
+            }
+        );
+        assert_eq!(
+            corrupt(&|store| store.elem_count = 4),
+            InfixStoreError::ElemCountMismatch {
+                elem_count: 4,
+                used_slots: 5
+            }
+        );
+        // a bitmap changed behind the cached popcounts
+        let error = corrupt(&|store| {
+            store.set_occupied(3, false);
+            store.set_occupied(600, true);
+        });
+        assert!(matches!(error, InfixStoreError::StalePopcounts { .. }));
+        assert_eq!(
+            corrupt(&|store| store.write_slot_at(1, 0)),
+            InfixStoreError::UnsortedRun {
+                quotient: 3,
+                slot: 1
+            }
+        );
+        assert_eq!(
+            corrupt(&|store| {
+                store.data.pop();
+            }),
+            InfixStoreError::DataLength {
+                words: store.data().len() - 1,
+                expected: store.data().len()
+            }
+        );
+        assert!(error.to_string().contains("cached popcounts"));
+
+        // updates in place keep every invariant
+        let mut store = InfixStore::new_with_infixes(&[], 8);
+        for i in 0..1500u64 {
+            store
+                .insert_resizing(((i * 13 % 1024) << 8) | (i % 256))
+                .unwrap();
+        }
+        assert_eq!(store.validate(), Ok(()));
+        for i in 0..1000u64 {
+            store
+                .remove_resizing(((i * 13 % 1024) << 8) | (i % 256))
+                .unwrap();
+        }
+        assert_eq!(store.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_quotient_size() {
+        assert_eq!(InfixStore::choose_quotient_size(0), MIN_QUOTIENT_SIZE);
+        assert_eq!(InfixStore::choose_quotient_size(16), 4);
+        assert_eq!(InfixStore::choose_quotient_size(17), 5);
+        assert_eq!(InfixStore::choose_quotient_size(1024), 10);
+        assert_eq!(InfixStore::choose_quotient_size(1 << 20), MAX_QUOTIENT_SIZE);
+
+        // the occupieds bitmap follows the quotient size
+        for quotient_size in [4, 14] {
+            let infixes: Vec<u64> = (0..12u64)
+                .map(|i| ((i * 5 % (1 << quotient_size)) << 8) | (i * 37 % 256) | 1)
+                .collect();
+            let mut sorted = infixes.clone();
+            sorted.sort();
+
+            let store = InfixStore::new_with_quotient_size(&sorted, quotient_size, 8);
+            assert_eq!(store.quotient_size(), quotient_size);
+            assert_eq!(store.num_quotients(), 1 << quotient_size);
+            assert_eq!(
+                store.data().len(),
+                1 + InfixStore::occupieds_words(quotient_size)
+                    + store.num_slots().div_ceil(U64_BITS)
+                    + (store.num_slots() * 8).div_ceil(U64_BITS)
+            );
+            assert_eq!(store.to_infixes(), sorted);
+            assert!(sorted.iter().all(|&infix| store.contains_infix(infix)));
+        }
+    }
 }
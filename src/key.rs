@@ -0,0 +1,133 @@
+use std::fmt::{Binary, Debug, Display};
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Not, Shl, Shr, Sub};
+
+/// Unsigned integer key stored in the tries and in Diva
+///
+/// The trie depth and all bit positions used for infix extraction follow `BITS`,
+/// so 32-bit ids use 32 levels while 128-bit keys use 128.
+pub trait UnsignedKey:
+    Copy
+    + Ord
+    + Hash
+    + Default
+    + Debug
+    + Display
+    + Binary
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+{
+    /// number of bits in the key
+    const BITS: usize;
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn leading_zeros(self) -> u32;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+
+    /// lowest 64 bits of the key
+    fn low_u64(self) -> u64;
+
+    /// key holding `value` in its lowest bits, truncated to the key width
+    fn from_u64(value: u64) -> Self;
+
+    /// nearest f64, used for size and rate estimates
+    fn to_f64(self) -> f64;
+
+    /// write the key as BITS / 8 little-endian bytes
+    fn write_le(self, writer: &mut impl Write) -> io::Result<()>;
+
+    /// read a key written by `write_le`
+    fn read_le(reader: &mut impl Read) -> io::Result<Self>;
+}
+
+macro_rules! impl_unsigned_key {
+    ($($t:ty),*) => {
+        $(
+            impl UnsignedKey for $t {
+                const BITS: usize = <$t>::BITS as usize;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+
+                fn leading_zeros(self) -> u32 {
+                    <$t>::leading_zeros(self)
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                fn low_u64(self) -> u64 {
+                    self as u64
+                }
+
+                fn from_u64(value: u64) -> Self {
+                    value as $t
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn write_le(self, writer: &mut impl Write) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn read_le(reader: &mut impl Read) -> io::Result<Self> {
+                    let mut bytes = [0u8; <$t>::BITS as usize / 8];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned_key!(u16, u32, u64, u128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<K: UnsignedKey>(key: K) -> K {
+        let mut bytes = Vec::new();
+        key.write_le(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), K::BITS / 8);
+        K::read_le(&mut bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_key_widths() {
+        assert_eq!(<u16 as UnsignedKey>::BITS, 16);
+        assert_eq!(<u32 as UnsignedKey>::BITS, 32);
+        assert_eq!(<u64 as UnsignedKey>::BITS, 64);
+        assert_eq!(<u128 as UnsignedKey>::BITS, 128);
+
+        assert_eq!(round_trip(0xbeefu16), 0xbeef);
+        assert_eq!(round_trip(u32::MAX - 7), u32::MAX - 7);
+        assert_eq!(round_trip(1u128 << 100 | 5), 1u128 << 100 | 5);
+        assert!(u32::read_le(&mut [1u8, 2].as_slice()).is_err());
+
+        assert_eq!((1u128 << 70 | 0xff).low_u64(), 0xff);
+        assert_eq!(u16::from_u64(0x1_0003), 3);
+    }
+}
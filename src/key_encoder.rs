@@ -0,0 +1,343 @@
+use crate::UnsignedKey;
+
+/// Order-preserving encoding of a native key type into an unsigned key
+///
+/// `a < b` must hold exactly when `a.encode() < b.encode()`, so range queries over
+/// native bounds can run on the encoded keys. `decode` inverts `encode`.
+///
+/// Floats have no such order under `<`, so `f32` and `f64` follow the IEEE total order
+/// of `total_cmp` instead: -0.0 sorts right below 0.0 and NaNs sort at the ends.
+pub trait KeyEncoder: Copy {
+    type Encoded: UnsignedKey;
+
+    fn encode(self) -> Self::Encoded;
+
+    fn decode(encoded: Self::Encoded) -> Self;
+}
+
+macro_rules! impl_unsigned_encoder {
+    ($($t:ty),*) => {
+        $(
+            impl KeyEncoder for $t {
+                type Encoded = $t;
+
+                fn encode(self) -> $t {
+                    self
+                }
+
+                fn decode(encoded: $t) -> Self {
+                    encoded
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned_encoder!(u16, u32, u64, u128);
+
+// flipping the sign bit moves negative values below the positive ones
+macro_rules! impl_signed_encoder {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl KeyEncoder for $t {
+                type Encoded = $u;
+
+                fn encode(self) -> $u {
+                    (self as $u) ^ (1 << (<$u>::BITS - 1))
+                }
+
+                fn decode(encoded: $u) -> Self {
+                    (encoded ^ (1 << (<$u>::BITS - 1))) as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_signed_encoder!(i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+// IEEE total order: negative values have all bits flipped so larger magnitudes sort
+// lower, positive values only get the sign bit set. -0.0 sorts right below 0.0 and
+// NaNs sort at the ends according to their sign
+macro_rules! impl_float_encoder {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl KeyEncoder for $t {
+                type Encoded = $u;
+
+                fn encode(self) -> $u {
+                    let bits = self.to_bits();
+                    if bits >> (<$u>::BITS - 1) == 1 {
+                        !bits
+                    } else {
+                        bits | (1 << (<$u>::BITS - 1))
+                    }
+                }
+
+                fn decode(encoded: $u) -> Self {
+                    let bits = if encoded >> (<$u>::BITS - 1) == 1 {
+                        encoded & !(1 << (<$u>::BITS - 1))
+                    } else {
+                        !encoded
+                    };
+                    <$t>::from_bits(bits)
+                }
+            }
+        )*
+    };
+}
+
+impl_float_encoder!(f32 => u32, f64 => u64);
+
+/// Unsigned key with a type twice as wide, used to concatenate tuple fields
+pub trait ConcatKey: UnsignedKey {
+    type Double: UnsignedKey;
+
+    /// `hi` in the upper half and `lo` in the lower half
+    fn concat(hi: Self, lo: Self) -> Self::Double;
+
+    /// inverse of `concat`, returns (hi, lo)
+    fn split(double: Self::Double) -> (Self, Self);
+}
+
+macro_rules! impl_concat_key {
+    ($($t:ty => $d:ty),*) => {
+        $(
+            impl ConcatKey for $t {
+                type Double = $d;
+
+                fn concat(hi: Self, lo: Self) -> $d {
+                    ((hi as $d) << <$t>::BITS) | lo as $d
+                }
+
+                fn split(double: $d) -> (Self, Self) {
+                    ((double >> <$t>::BITS) as $t, double as $t)
+                }
+            }
+        )*
+    };
+}
+
+impl_concat_key!(u16 => u32, u32 => u64, u64 => u128);
+
+// lexicographic order on pairs is the order of the concatenated encodings
+impl<A, B> KeyEncoder for (A, B)
+where
+    A: KeyEncoder,
+    A::Encoded: ConcatKey,
+    B: KeyEncoder<Encoded = A::Encoded>,
+{
+    type Encoded = <A::Encoded as ConcatKey>::Double;
+
+    fn encode(self) -> Self::Encoded {
+        A::Encoded::concat(self.0.encode(), self.1.encode())
+    }
+
+    fn decode(encoded: Self::Encoded) -> Self {
+        let (hi, lo) = A::Encoded::split(encoded);
+        (A::decode(hi), B::decode(lo))
+    }
+}
+
+/// Calendar date in the proleptic Gregorian calendar
+///
+/// Encoded as the number of days since 1970-01-01, so consecutive days get consecutive
+/// keys and a range of n days covers n keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// the date, `None` if the month or the day does not exist or if the date is too far
+    /// from 1970-01-01 for its days to fit an `i32`
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Self> {
+        let days_in_month = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            _ => return None,
+        };
+        if !(1..=days_in_month).contains(&day) {
+            return None;
+        }
+        let date = Self { year, month, day };
+        i32::try_from(date.days_since_epoch_i64()).ok()?;
+        Some(date)
+    }
+
+    /// days since 1970-01-01, negative before it
+    ///
+    /// The fields must hold a valid date, as checked by `Date::new`.
+    pub fn days_since_epoch(&self) -> i32 {
+        self.days_since_epoch_i64() as i32
+    }
+
+    fn days_since_epoch_i64(&self) -> i64 {
+        // shift the year to start in march so the leap day ends the year
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// inverse of `days_since_epoch`
+    pub fn from_days_since_epoch(days: i32) -> Self {
+        let days = days as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u8;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u8;
+        let year = (year_of_era + era * 400 + (month <= 2) as i64) as i32;
+        Self { year, month, day }
+    }
+}
+
+impl KeyEncoder for Date {
+    type Encoded = u32;
+
+    fn encode(self) -> u32 {
+        self.days_since_epoch().encode()
+    }
+
+    fn decode(encoded: u32) -> Self {
+        Self::from_days_since_epoch(i32::decode(encoded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_gen::DataGen;
+
+    fn assert_order_preserved<T: KeyEncoder + PartialOrd + std::fmt::Debug>(values: &[T]) {
+        for a in values {
+            assert_eq!(
+                T::decode(a.encode()).partial_cmp(a),
+                Some(std::cmp::Ordering::Equal)
+            );
+            for b in values {
+                assert_eq!(
+                    a.partial_cmp(b),
+                    Some(a.encode().cmp(&b.encode())),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_order() {
+        assert_order_preserved(&[i32::MIN, -5, -1, 0, 1, 7, i32::MAX]);
+        assert_order_preserved(&[i64::MIN, -1, 0, i64::MAX]);
+        assert_order_preserved(&[i16::MIN, -300, 0, 300, i16::MAX]);
+        assert_eq!(0i32.encode(), 1 << 31);
+        assert_order_preserved(&DataGen::new(42).smooth_i32(Some(200)));
+    }
+
+    #[test]
+    fn test_float_order() {
+        assert_order_preserved(&[
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            0.0,
+            f64::MIN_POSITIVE,
+            2.25,
+            f64::MAX,
+            f64::INFINITY,
+        ]);
+        assert_order_preserved(&[-3.5f32, -0.25, 0.0, 1e-30, 8.0]);
+
+        // total order puts -0.0 right below 0.0 and NaNs at the ends
+        assert_eq!((-0.0f64).encode() + 1, 0.0f64.encode());
+        assert!(f64::NAN.encode() > f64::INFINITY.encode());
+        assert!((-f64::NAN).encode() < f64::NEG_INFINITY.encode());
+        assert!(f64::decode(f64::NAN.encode()).is_nan());
+        let floats = [f64::NAN, -0.0, 0.0, -f64::NAN, f64::INFINITY, -1.5];
+        for a in floats {
+            for b in floats {
+                assert_eq!(a.total_cmp(&b), a.encode().cmp(&b.encode()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tuple_order() {
+        let pairs: Vec<(u32, u32)> = DataGen::new(42)
+            .uniform_u64(200, 0, u64::MAX)
+            .into_iter()
+            .map(|v| ((v >> 60) as u32, v as u32))
+            .collect();
+        assert_order_preserved(&pairs);
+        assert_order_preserved(&[(-2i32, 5i32), (-2, 6), (0, i32::MIN), (3, -1)]);
+        assert_eq!((1u32, 2u32).encode(), (1u64 << 32) | 2);
+        assert_eq!(<(i64, u64)>::decode((-7i64, 9u64).encode()), (-7, 9));
+    }
+
+    #[test]
+    fn test_date_encoding() {
+        assert_eq!(Date::new(1970, 1, 1).unwrap().days_since_epoch(), 0);
+        assert_eq!(Date::new(2000, 3, 1).unwrap().days_since_epoch(), 11017);
+        assert_eq!(Date::new(1969, 12, 31).unwrap().days_since_epoch(), -1);
+        assert_eq!(
+            Date::new(2024, 3, 1).unwrap().days_since_epoch()
+                - Date::new(2024, 2, 28).unwrap().days_since_epoch(),
+            2
+        );
+
+        for days in (-800_000..800_000).step_by(997) {
+            assert_eq!(Date::from_days_since_epoch(days).days_since_epoch(), days);
+        }
+        assert_order_preserved(&[
+            Date::new(-44, 3, 15).unwrap(),
+            Date::new(1969, 12, 31).unwrap(),
+            Date::new(1970, 1, 1).unwrap(),
+            Date::new(2024, 2, 29).unwrap(),
+            Date::new(2024, 3, 1).unwrap(),
+        ]);
+    }
+
+    #[test]
+    fn test_date_validation() {
+        assert_eq!(
+            Date::new(2024, 2, 29),
+            Some(Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            })
+        );
+        assert!(Date::new(2000, 2, 29).is_some());
+        assert!(Date::new(1900, 2, 29).is_none());
+        assert!(Date::new(2023, 2, 29).is_none());
+        assert!(Date::new(2024, 4, 31).is_none());
+        assert!(Date::new(2024, 13, 1).is_none());
+        assert!(Date::new(2024, 0, 1).is_none());
+        assert!(Date::new(2024, 1, 0).is_none());
+
+        // days must fit an i32
+        let last = Date::from_days_since_epoch(i32::MAX);
+        assert_eq!(Date::new(last.year, last.month, last.day), Some(last));
+        assert!(Date::new(i32::MAX, 1, 1).is_none());
+        assert!(Date::new(i32::MIN, 1, 1).is_none());
+    }
+}
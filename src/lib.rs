@@ -1,18 +1,128 @@
 pub mod binary_search_tree;
 pub mod bitmap;
 pub mod data_gen;
+pub mod datasets;
 pub mod diva;
+pub mod evaluation;
 pub mod infix_store;
+pub mod key;
+pub mod key_encoder;
+pub mod memory;
+pub mod string_diva;
+pub mod string_x_fast_trie;
 pub mod utils;
 pub mod x_fast_trie;
 pub mod y_fast_trie;
 
 pub use binary_search_tree::BinarySearchTreeGroup;
 pub use bitmap::{get_bit, rank, select, set_bit};
-pub use diva::Diva;
-pub use infix_store::InfixStore;
+pub use diva::{Diva, DivaConfig, DivaPlan, DivaStats, MemoryBudget};
+pub use evaluation::{Evaluation, evaluate};
+pub use infix_store::{InfixStore, InfixStoreError};
+pub use key::UnsignedKey;
+pub use key_encoder::{Date, KeyEncoder};
+pub use memory::MemoryUsage;
+pub use string_diva::StringDiva;
+pub use string_x_fast_trie::StringXFastTrie;
 pub use x_fast_trie::{RepNode, XFastLevel, XFastTrie, XFastValue};
 pub use y_fast_trie::YFastTrie;
 
 pub type Key = u64;
 pub const U64_BITS: usize = 64;
+
+/// Common interface of range filters
+///
+/// Filters answer point and range queries with no false negatives. Filters that can
+/// be updated after construction report it through `supports_insert`/`supports_delete`,
+/// the others keep the default implementations which return `Err(Unsupported)`.
+pub trait RangeFilter<K: UnsignedKey = Key> {
+    /// Parameters needed to build the filter besides the keys
+    type Config;
+
+    /// Build a filter over a set of keys
+    fn from_keys(keys: &[K], config: &Self::Config) -> Self
+    where
+        Self: Sized;
+
+    /// Returns false only if the key was not inserted
+    fn may_contain(&self, key: K) -> bool;
+
+    /// Returns false only if no key in `[lo, hi]` was inserted
+    fn may_contain_range(&self, lo: K, hi: K) -> bool;
+
+    /// Size of the filter in bits
+    fn size_in_bits(&self) -> usize;
+
+    fn supports_insert(&self) -> bool {
+        false
+    }
+
+    fn insert(&mut self, _key: K) -> Result<(), Unsupported> {
+        Err(Unsupported)
+    }
+
+    fn supports_delete(&self) -> bool {
+        false
+    }
+
+    /// Returns Ok(false) if the key was not found
+    fn delete(&mut self, _key: K) -> Result<bool, Unsupported> {
+        Err(Unsupported)
+    }
+}
+
+/// Error returned by range filter updates the filter does not support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unsupported;
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "operation not supported by this range filter")
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // exact filter with no update support, to exercise the default methods
+    struct ExactFilter(BTreeSet<Key>);
+
+    impl RangeFilter for ExactFilter {
+        type Config = ();
+
+        fn from_keys(keys: &[Key], _config: &()) -> Self {
+            Self(keys.iter().copied().collect())
+        }
+
+        fn may_contain(&self, key: Key) -> bool {
+            self.0.contains(&key)
+        }
+
+        fn may_contain_range(&self, lo: Key, hi: Key) -> bool {
+            lo <= hi && self.0.range(lo..=hi).next().is_some()
+        }
+
+        fn size_in_bits(&self) -> usize {
+            self.0.len() * U64_BITS
+        }
+    }
+
+    #[test]
+    fn test_default_updates_unsupported() {
+        let mut filter = ExactFilter::from_keys(&[1, 5, 9], &());
+        assert!(filter.may_contain(5));
+        assert!(filter.may_contain_range(2, 6));
+        assert!(!filter.may_contain_range(6, 8));
+        assert_eq!(filter.size_in_bits(), 192);
+
+        assert!(!filter.supports_insert());
+        assert!(!filter.supports_delete());
+        assert_eq!(filter.insert(3), Err(Unsupported));
+        assert_eq!(filter.delete(5), Err(Unsupported));
+        assert!(filter.may_contain(5));
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// Estimated bytes used by each part of a Diva: its y-fast trie and the infix stores
+/// attached to it
+///
+/// # Arguments
+/// * `x_fast_levels` - Bytes of the x-fast trie levels, their hash tables and child nodes
+/// * `rep_nodes` - Bytes of the representatives linking the BST groups
+/// * `bst_nodes` - Bytes of the BST groups and their tree nodes
+/// * `infix_stores` - Bytes of the infix stores and their data words
+/// * `filter_fields` - Bytes of the filter's own fields besides its y-fast trie
+/// * `key_count` - Number of keys the memory is spent on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryUsage {
+    pub x_fast_levels: usize,
+    pub rep_nodes: usize,
+    pub bst_nodes: usize,
+    pub infix_stores: usize,
+    pub filter_fields: usize,
+    pub key_count: usize,
+}
+
+impl MemoryUsage {
+    pub fn total_bytes(&self) -> usize {
+        self.x_fast_levels
+            + self.rep_nodes
+            + self.bst_nodes
+            + self.infix_stores
+            + self.filter_fields
+    }
+
+    pub fn bits_per_key(&self) -> f64 {
+        if self.key_count == 0 {
+            return 0.0;
+        }
+        (self.total_bytes() * 8) as f64 / self.key_count as f64
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "  X-fast levels:        {} bytes", self.x_fast_levels)?;
+        writeln!(f, "  Rep nodes:            {} bytes", self.rep_nodes)?;
+        writeln!(f, "  BST nodes:            {} bytes", self.bst_nodes)?;
+        writeln!(f, "  Infix stores:         {} bytes", self.infix_stores)?;
+        writeln!(f, "  Filter fields:        {} bytes", self.filter_fields)?;
+        writeln!(f, "  Total:                {} bytes", self.total_bytes())?;
+        writeln!(f, "  Bits per key:         {:.2}", self.bits_per_key())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,412 @@
+use crate::Key;
+use crate::U64_BITS;
+use crate::diva::Diva;
+use crate::infix_store::InfixStore;
+use crate::string_x_fast_trie::StringXFastTrie;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Diva range filter over variable-length byte-string keys
+///
+/// Keys are compared lexicographically and read as bit strings padded with zeros, which
+/// keeps their order. Samples are stored exactly in a `StringXFastTrie`, and each key
+/// between two samples is stored as the infix taken from the bits after the shared
+/// prefix of the two samples, so arbitrarily long shared prefixes cost nothing.
+///
+/// # Arguments
+/// * `samples` - String X-Fast Trie holding the sampled keys
+/// * `infix_stores` - Infix store of every sample with keys before the next sample
+/// * `target_size` - Target size
+/// * `fpr` - False positive rate
+/// * `remainder_size` - Remainder size
+///
+/// # Example
+/// ```rust
+/// use range_filters::string_diva::StringDiva;
+/// let keys = ["apple", "banana", "cherry", "date"];
+/// let diva = StringDiva::new_with_keys(&keys, 2, 0.01);
+/// assert!(diva.may_contain(b"banana"));
+/// assert!(diva.may_contain_prefix(b"ch"));
+/// ```
+pub struct StringDiva {
+    samples: StringXFastTrie,
+    infix_stores: HashMap<Vec<u8>, InfixStore>,
+    target_size: usize,
+    fpr: f64,
+    remainder_size: u8,
+}
+
+impl StringDiva {
+    /// Build a filter over a set of string keys
+    ///
+    /// # Panics
+    /// If `target_size` is 0 or above `MAX_ELEMENTS`, the most infixes a store holds.
+    pub fn new_with_keys<T: AsRef<[u8]>>(keys: &[T], target_size: usize, fpr: f64) -> Self {
+        Diva::<Key>::check_target_size(target_size);
+        let remainder_size = Diva::<Key>::choose_remainder_size(target_size, fpr);
+        let mut sorted_keys: Vec<&[u8]> = keys.iter().map(|key| key.as_ref()).collect();
+        sorted_keys.sort();
+        sorted_keys.dedup();
+
+        let mut sampled_keys: Vec<&[u8]> =
+            sorted_keys.iter().step_by(target_size).copied().collect();
+
+        // ensure last key is sampled if not already
+        if let Some(&last_key) = sorted_keys.last()
+            && sampled_keys.last() != Some(&last_key)
+        {
+            sampled_keys.push(last_key);
+        }
+
+        let samples = StringXFastTrie::new_with_keys(&sampled_keys);
+
+        // keys are sorted, so the keys between two samples are the run between them
+        let mut infix_stores = HashMap::new();
+        for (i, chunk) in sorted_keys.chunks(target_size).enumerate() {
+            let predecessor = sampled_keys[i];
+            let Some(&successor) = sampled_keys.get(i + 1) else {
+                break;
+            };
+
+            let keys: Vec<&[u8]> = chunk[1..]
+                .iter()
+                .copied()
+                .filter(|&key| key != successor)
+                .collect();
+            let quotient_size = InfixStore::choose_quotient_size(keys.len());
+            let infixes: Vec<u64> = keys
+                .iter()
+                .map(|key| {
+                    Self::extract_infix(
+                        key,
+                        predecessor,
+                        successor,
+                        quotient_size,
+                        remainder_size,
+                        0,
+                    )
+                })
+                .collect();
+
+            if !infixes.is_empty() {
+                let infix_store =
+                    InfixStore::new_with_quotient_size(&infixes, quotient_size, remainder_size);
+                infix_stores.insert(predecessor.to_vec(), infix_store);
+            }
+        }
+
+        Self {
+            samples,
+            infix_stores,
+            target_size,
+            fpr,
+            remainder_size,
+        }
+    }
+
+    /// Point membership query
+    ///
+    /// Sampled keys are answered exactly. Any other key is answered from the infix
+    /// store of its predecessor sample, so a true result may be a false positive.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        if self.samples.contains(key) {
+            return true;
+        }
+
+        let (Some(predecessor), Some(successor)) =
+            (self.samples.predecessor(key), self.samples.successor(key))
+        else {
+            return false;
+        };
+        let Some(infix_store) = self.infix_stores.get(predecessor) else {
+            return false;
+        };
+
+        let infix = Self::extract_infix(
+            key,
+            predecessor,
+            successor,
+            infix_store.quotient_size(),
+            self.remainder_size,
+            0,
+        );
+        infix_store.contains_infix(infix)
+    }
+
+    /// Range emptiness query
+    ///
+    /// Returns false only if no key in `[lo, hi]` was inserted. A true result may be a
+    /// false positive.
+    ///
+    /// # Arguments
+    /// * `lo` - Lower bound of the range (inclusive)
+    /// * `hi` - Upper bound of the range (inclusive)
+    pub fn may_contain_range(&self, lo: &[u8], hi: &[u8]) -> bool {
+        if lo > hi {
+            return false;
+        }
+
+        let Some(successor) = self.samples.successor(lo) else {
+            return false;
+        };
+        if successor <= hi {
+            return true;
+        }
+
+        self.bucket_overlaps(lo, hi, 0)
+    }
+
+    /// Prefix query
+    ///
+    /// Returns false only if no inserted key starts with `prefix`. A true result may be
+    /// a false positive.
+    pub fn may_contain_prefix(&self, prefix: &[u8]) -> bool {
+        let Some(successor) = self.samples.successor(prefix) else {
+            return false;
+        };
+        if successor.starts_with(prefix) {
+            return true;
+        }
+
+        // keys starting with the prefix lie between the prefix itself and the prefix
+        // followed by an endless run of ones
+        self.bucket_overlaps(prefix, prefix, 1)
+    }
+
+    /// check the infix store of the bucket holding `[lo, hi]`, where `hi` is padded
+    /// with `hi_padding` and the bucket's successor sample lies above the range
+    fn bucket_overlaps(&self, lo: &[u8], hi: &[u8], hi_padding: u8) -> bool {
+        let (Some(predecessor), Some(successor)) =
+            (self.samples.predecessor(lo), self.samples.successor(lo))
+        else {
+            return false;
+        };
+        let Some(infix_store) = self.infix_stores.get(predecessor) else {
+            return false;
+        };
+
+        let quotient_size = infix_store.quotient_size();
+        let lo_infix = Self::extract_infix(
+            lo,
+            predecessor,
+            successor,
+            quotient_size,
+            self.remainder_size,
+            0,
+        );
+        let hi_infix = Self::extract_infix(
+            hi,
+            predecessor,
+            successor,
+            quotient_size,
+            self.remainder_size,
+            hi_padding,
+        );
+        infix_store.any_in_range(lo_infix, hi_infix)
+    }
+
+    /// extract the infix of a key lying between two consecutive samples
+    /// layout matches Diva: [MSB: 1 bit][payload][terminator: 1 bit], where the MSB is
+    /// the key's bit at the first position where the padded samples differ
+    ///
+    /// # Arguments
+    /// * `quotient_size` - Quotient size of the bucket's infix store
+    /// * `padding` - Bit read past the end of `key`
+    fn extract_infix(
+        key: &[u8],
+        predecessor: &[u8],
+        successor: &[u8],
+        quotient_size: u8,
+        remainder_size: u8,
+        padding: u8,
+    ) -> u64 {
+        let shared_prefix_len = Self::first_differing_bit(predecessor, successor);
+        let payload_bits = (quotient_size as usize + remainder_size as usize) - 2;
+
+        let msb = Self::bit_at(key, shared_prefix_len, padding) as u64;
+        let payload = (0..payload_bits).fold(0u64, |payload, i| {
+            (payload << 1) | Self::bit_at(key, shared_prefix_len + 1 + i, padding) as u64
+        });
+
+        (((msb << payload_bits) | payload) << 1) | 1
+    }
+
+    /// first bit position where two zero padded keys differ
+    fn first_differing_bit(key_1: &[u8], key_2: &[u8]) -> usize {
+        let len = key_1.len().max(key_2.len());
+        (0..len)
+            .find_map(|i| {
+                let byte_1 = key_1.get(i).copied().unwrap_or(0);
+                let byte_2 = key_2.get(i).copied().unwrap_or(0);
+                let diff = byte_1 ^ byte_2;
+                (diff != 0).then(|| i * 8 + diff.leading_zeros() as usize)
+            })
+            .unwrap_or(len * 8)
+    }
+
+    /// bit `pos` of a key, counting from the most significant bit of the first byte
+    fn bit_at(key: &[u8], pos: usize, padding: u8) -> u8 {
+        match key.get(pos / 8) {
+            Some(byte) => (byte >> (7 - pos % 8)) & 1,
+            None => padding,
+        }
+    }
+
+    /// bits used by the sampled keys and the infix stores
+    pub fn size_in_bits(&self) -> usize {
+        let sample_bits: usize = self.samples.keys().iter().map(|key| key.len() * 8).sum();
+        let infix_store_words: usize = self
+            .infix_stores
+            .values()
+            .map(|store| store.data().len())
+            .sum();
+        sample_bits + infix_store_words * U64_BITS
+    }
+
+    pub fn pretty_print(&self) {
+        print!("{}", self);
+    }
+}
+
+impl fmt::Display for StringDiva {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "\n=== String Diva ===")?;
+        writeln!(f, "  Target size:    {}", self.target_size)?;
+        writeln!(f, "  FPR:            {}", self.fpr)?;
+        writeln!(f, "  Remainder size: {}", self.remainder_size)?;
+        writeln!(f, "  Samples:        {}", self.samples.len())?;
+        for sample in self.samples.keys() {
+            let elem_count = self
+                .infix_stores
+                .get(sample)
+                .map_or(0, |store| store.elem_count());
+            writeln!(
+                f,
+                "  {} ({} infixes)",
+                String::from_utf8_lossy(sample),
+                elem_count
+            )?;
+        }
+        writeln!(f, "=== End Structure ===\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_gen::DataGen;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_extract_infix_order() {
+        let predecessor = b"user/0000000000/alpha";
+        let successor = b"user/0000000000/omega";
+        assert_eq!(
+            StringDiva::first_differing_bit(predecessor, successor),
+            16 * 8 + 4
+        );
+        assert_eq!(StringDiva::first_differing_bit(b"ab", b"ab\0"), 24);
+
+        // infixes follow the key order between the samples
+        let keys: [&[u8]; 4] = [
+            b"user/0000000000/alpha",
+            b"user/0000000000/beta",
+            b"user/0000000000/gamma",
+            b"user/0000000000/omega",
+        ];
+        let infixes: Vec<u64> = keys
+            .iter()
+            .map(|key| StringDiva::extract_infix(key, predecessor, successor, 10, 8, 0))
+            .collect();
+        assert!(infixes.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // padding with ones bounds every key starting with the prefix
+        let hi = StringDiva::extract_infix(b"user/0000000000/g", predecessor, successor, 10, 8, 1);
+        assert!(infixes[2] <= hi && hi < infixes[3]);
+    }
+
+    #[test]
+    fn test_no_false_negatives() {
+        let keys = DataGen::new(42).strings(5000, 1, 16);
+        let diva = StringDiva::new_with_keys(&keys, 64, 0.01);
+
+        for key in &keys {
+            assert!(diva.may_contain(key.as_bytes()), "false negative: {}", key);
+            assert!(diva.may_contain_range(key.as_bytes(), key.as_bytes()));
+            for len in 0..=key.len() {
+                let prefix = &key.as_bytes()[..len];
+                assert!(diva.may_contain_prefix(prefix), "prefix {:?}", prefix);
+            }
+        }
+
+        let mut sorted = keys.clone();
+        sorted.sort();
+        for pair in sorted.windows(2).step_by(7) {
+            assert!(diva.may_contain_range(pair[0].as_bytes(), pair[1].as_bytes()));
+        }
+        assert!(!diva.may_contain_range(b"z", b"a"));
+    }
+
+    #[test]
+    #[should_panic(expected = "target size 0 is not in 1..=2326")]
+    fn test_target_size_zero() {
+        StringDiva::new_with_keys(&["apple", "banana"], 0, 0.01);
+    }
+
+    #[test]
+    #[should_panic(expected = "target size 5000 is not in 1..=2326")]
+    fn test_target_size_too_large() {
+        let keys = DataGen::new(42).strings(10000, 4, 12);
+        StringDiva::new_with_keys(&keys, 5000, 0.01);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut data_gen = DataGen::new(42);
+        // long shared prefixes beyond the 64 bit trie prefix
+        let keys: Vec<String> = data_gen
+            .strings(10000, 4, 12)
+            .into_iter()
+            .map(|s| format!("tenant/0042/{}", s))
+            .collect();
+        let diva = StringDiva::new_with_keys(&keys, 64, 0.0001);
+
+        // empty prefixes and ranges outside every key
+        assert!(!diva.may_contain_prefix(b"tenant/0043/"));
+        assert!(!diva.may_contain_range(b"a", b"b"));
+        assert!(!diva.may_contain_range(b"zz", b"zzz"));
+        let key_bits: usize = keys.iter().map(|key| key.len() * 8).sum();
+        assert!(diva.size_in_bits() < key_bits);
+
+        // the configured fpr assumes uniform bits after the shared prefix,
+        // which lowercase text does not have
+        let fpr = 0.0001;
+        let key = |value: u64| [b"tenant/0042/".as_slice(), &value.to_be_bytes()].concat();
+        let keys: Vec<Vec<u8>> = data_gen
+            .uniform_u64(10000, 0, u64::MAX)
+            .into_iter()
+            .map(key)
+            .collect();
+        let key_set: BTreeSet<&[u8]> = keys.iter().map(|key| key.as_slice()).collect();
+        let diva = StringDiva::new_with_keys(&keys, 64, fpr);
+
+        let queries: Vec<Vec<u8>> = data_gen
+            .uniform_u64(100000, 0, u64::MAX)
+            .into_iter()
+            .map(key)
+            .filter(|query| !key_set.contains(query.as_slice()))
+            .collect();
+        let false_positives = queries
+            .iter()
+            .filter(|query| diva.may_contain(query))
+            .count();
+        let point_fpr = false_positives as f64 / queries.len() as f64;
+        assert!(
+            point_fpr < fpr * 10.0,
+            "point fpr {} configured {}",
+            point_fpr,
+            fpr
+        );
+    }
+}
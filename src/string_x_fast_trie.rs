@@ -0,0 +1,235 @@
+use crate::x_fast_trie::XFastTrie;
+use std::collections::HashMap;
+use std::fmt;
+
+const PREFIX_BYTES: usize = 8;
+
+/// X-fast trie over variable-length byte strings
+///
+/// The trie indexes the first 8 bytes of every key as a big-endian u64, zero padded.
+/// That prefix preserves lexicographic order, so keys with a smaller prefix always
+/// sort first. Keys sharing a prefix are kept sorted in a bucket under it.
+#[derive(Debug)]
+pub struct StringXFastTrie {
+    pub x_fast_trie: XFastTrie<u64>,
+    pub buckets: HashMap<u64, Vec<Vec<u8>>>,
+}
+
+impl Default for StringXFastTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringXFastTrie {
+    pub fn new() -> Self {
+        Self {
+            x_fast_trie: XFastTrie::new(u64::BITS as usize),
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn new_with_keys<T: AsRef<[u8]>>(keys: &[T]) -> Self {
+        let mut trie = Self::new();
+        for key in keys {
+            trie.insert(key.as_ref());
+        }
+        trie
+    }
+
+    /// order-preserving 64 bit prefix of a key
+    pub fn key_prefix(key: &[u8]) -> u64 {
+        let mut bytes = [0u8; PREFIX_BYTES];
+        let len = key.len().min(PREFIX_BYTES);
+        bytes[..len].copy_from_slice(&key[..len]);
+        u64::from_be_bytes(bytes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.values().map(|bucket| bucket.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.buckets
+            .get(&Self::key_prefix(key))
+            .is_some_and(|bucket| bucket.binary_search_by(|k| k.as_slice().cmp(key)).is_ok())
+    }
+
+    pub fn insert(&mut self, key: &[u8]) {
+        let prefix = Self::key_prefix(key);
+        let bucket = self.buckets.entry(prefix).or_default();
+        if let Err(position) = bucket.binary_search_by(|k| k.as_slice().cmp(key)) {
+            bucket.insert(position, key.to_vec());
+        }
+        if bucket.len() == 1 {
+            self.x_fast_trie.insert(prefix);
+        }
+    }
+
+    // remove a key, returns false if the key is not in the trie
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let prefix = Self::key_prefix(key);
+        let Some(bucket) = self.buckets.get_mut(&prefix) else {
+            return false;
+        };
+        let Ok(position) = bucket.binary_search_by(|k| k.as_slice().cmp(key)) else {
+            return false;
+        };
+
+        bucket.remove(position);
+        if bucket.is_empty() {
+            self.buckets.remove(&prefix);
+            self.x_fast_trie.remove(prefix);
+        }
+        true
+    }
+
+    /// largest key <= `key`
+    pub fn predecessor(&self, key: &[u8]) -> Option<&[u8]> {
+        let prefix = Self::key_prefix(key);
+        if let Some(bucket) = self.buckets.get(&prefix) {
+            let position = bucket.partition_point(|k| k.as_slice() <= key);
+            if position > 0 {
+                return Some(&bucket[position - 1]);
+            }
+        }
+
+        // every key under a smaller prefix sorts before `key`
+        let rep_node = self.x_fast_trie.predecessor(prefix.checked_sub(1)?)?;
+        let lower_prefix = rep_node.read().ok()?.key;
+        self.buckets
+            .get(&lower_prefix)?
+            .last()
+            .map(|k| k.as_slice())
+    }
+
+    /// smallest key >= `key`
+    pub fn successor(&self, key: &[u8]) -> Option<&[u8]> {
+        let prefix = Self::key_prefix(key);
+        if let Some(bucket) = self.buckets.get(&prefix) {
+            let position = bucket.partition_point(|k| k.as_slice() < key);
+            if position < bucket.len() {
+                return Some(&bucket[position]);
+            }
+        }
+
+        // every key under a larger prefix sorts after `key`
+        let rep_node = self.x_fast_trie.successor(prefix.checked_add(1)?)?;
+        let upper_prefix = rep_node.read().ok()?.key;
+        self.buckets
+            .get(&upper_prefix)?
+            .first()
+            .map(|k| k.as_slice())
+    }
+
+    /// all keys in sorted order
+    pub fn keys(&self) -> Vec<&[u8]> {
+        let mut prefixes: Vec<u64> = self.buckets.keys().copied().collect();
+        prefixes.sort();
+        prefixes
+            .iter()
+            .flat_map(|prefix| self.buckets[prefix].iter().map(|k| k.as_slice()))
+            .collect()
+    }
+
+    pub fn pretty_print(&self) {
+        print!("{}", self);
+    }
+}
+
+impl fmt::Display for StringXFastTrie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "\n=== String X-Fast Trie ===")?;
+        writeln!(f, "  Keys:     {}", self.len())?;
+        writeln!(f, "  Prefixes: {}", self.buckets.len())?;
+        for key in self.keys() {
+            writeln!(f, "  {}", String::from_utf8_lossy(key))?;
+        }
+        writeln!(f, "=== End Structure ===\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_gen::DataGen;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_key_prefix_preserves_order() {
+        assert_eq!(StringXFastTrie::key_prefix(b""), 0);
+        assert_eq!(StringXFastTrie::key_prefix(b"a"), 0x61 << 56);
+        assert_eq!(
+            StringXFastTrie::key_prefix(b"abcdefghij"),
+            StringXFastTrie::key_prefix(b"abcdefgh")
+        );
+
+        let mut keys = DataGen::new(42).strings(1000, 0, 12);
+        keys.sort();
+        for pair in keys.windows(2) {
+            assert!(
+                StringXFastTrie::key_prefix(pair[0].as_bytes())
+                    <= StringXFastTrie::key_prefix(pair[1].as_bytes())
+            );
+        }
+    }
+
+    #[test]
+    fn test_predecessor_successor() {
+        let mut data_gen = DataGen::new(42);
+        // long shared prefixes put many keys in the same bucket
+        let mut keys: Vec<Vec<u8>> = data_gen
+            .strings(2000, 1, 6)
+            .into_iter()
+            .map(|s| s.into_bytes())
+            .collect();
+        keys.extend((0..200).map(|i| format!("user/000{}", i * 7).into_bytes()));
+        let trie = StringXFastTrie::new_with_keys(&keys);
+        let oracle: BTreeSet<Vec<u8>> = keys.iter().cloned().collect();
+        assert_eq!(trie.len(), oracle.len());
+        assert_eq!(
+            trie.keys(),
+            oracle.iter().map(|k| k.as_slice()).collect::<Vec<_>>()
+        );
+
+        let queries: Vec<Vec<u8>> = data_gen
+            .strings(2000, 0, 12)
+            .into_iter()
+            .map(|s| s.into_bytes())
+            .chain((0..300).map(|i| format!("user/000{}", i * 3).into_bytes()))
+            .collect();
+        for query in &queries {
+            let expected_pred = oracle.range(..=query.clone()).next_back();
+            let expected_succ = oracle.range(query.clone()..).next();
+            assert_eq!(trie.predecessor(query), expected_pred.map(|k| k.as_slice()));
+            assert_eq!(trie.successor(query), expected_succ.map(|k| k.as_slice()));
+            assert_eq!(trie.contains(query), oracle.contains(query));
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let keys = ["apple", "applesauce", "applesauce pie", "banana", "b"];
+        let mut trie = StringXFastTrie::new_with_keys(&keys);
+
+        assert!(trie.remove(b"applesauce"));
+        assert!(!trie.remove(b"applesauce"));
+        assert!(!trie.remove(b"cherry"));
+        assert_eq!(trie.successor(b"applesauce"), Some(&b"applesauce pie"[..]));
+
+        assert!(trie.remove(b"applesauce pie"));
+        assert_eq!(trie.successor(b"applesauce"), Some(&b"b"[..]));
+        assert_eq!(trie.predecessor(b"az"), Some(&b"apple"[..]));
+
+        for key in ["apple", "banana", "b"] {
+            assert!(trie.remove(key.as_bytes()));
+        }
+        assert!(trie.is_empty());
+        assert_eq!(trie.predecessor(b"zzz"), None);
+    }
+}
@@ -1,5 +1,11 @@
-use crate::Key;
+use crate::UnsignedKey;
+use std::mem::size_of;
 
-pub fn longest_common_prefix_length(key1: Key, key2: Key) -> u32 {
+pub fn longest_common_prefix_length<K: UnsignedKey>(key1: K, key2: K) -> u32 {
     (key1 ^ key2).leading_zeros()
 }
+
+/// bytes of the heap allocation behind an `Arc<T>`, the value plus both reference counts
+pub fn arc_allocation_size<T>() -> usize {
+    2 * size_of::<usize>() + size_of::<T>()
+}
@@ -1,54 +1,58 @@
 use crate::Key;
+use crate::UnsignedKey;
 use crate::binary_search_tree::BinarySearchTreeGroup;
+use crate::utils::arc_allocation_size;
 use dashmap::DashMap;
 use std::fmt;
+use std::mem::size_of;
 use std::sync::{Arc, RwLock, Weak};
 
-pub const ROOT_KEY: Key = 67;
+pub const ROOT_KEY: u64 = 67;
 
 #[derive(Debug)]
-pub struct XFastTrie {
-    pub levels: Vec<XFastLevel>,
+pub struct XFastTrie<K: UnsignedKey = Key> {
+    pub levels: Vec<XFastLevel<K>>,
     // representatives
-    // pub reps: HashMap<Key, Arc<RwLock<RepNode>>>,
-    pub head_rep: Option<Arc<RwLock<RepNode>>>,
-    pub tail_rep: Option<Arc<RwLock<RepNode>>>,
+    // pub reps: HashMap<K, Arc<RwLock<RepNode<K>>>>,
+    pub head_rep: Option<Arc<RwLock<RepNode<K>>>>,
+    pub tail_rep: Option<Arc<RwLock<RepNode<K>>>>,
 
     // no. of levels = no. of bits in the keys
     pub no_levels: usize,
 }
 
 #[derive(Debug, Default, Clone)]
-pub struct XFastLevel {
-    pub table: DashMap<Key, XFastValue>,
+pub struct XFastLevel<K: UnsignedKey = Key> {
+    pub table: DashMap<K, XFastValue<K>>,
 }
 
 #[derive(Debug, Default, Clone)]
-pub struct XFastValue {
-    pub left_child: Option<Arc<RwLock<XFastValue>>>,
-    pub right_child: Option<Arc<RwLock<XFastValue>>>,
+pub struct XFastValue<K: UnsignedKey = Key> {
+    pub left_child: Option<Arc<RwLock<XFastValue<K>>>>,
+    pub right_child: Option<Arc<RwLock<XFastValue<K>>>>,
 
-    // pub representative: Option<Arc<RwLock<RepNode>>>
-    pub min_rep: Option<Arc<RwLock<RepNode>>>,
-    pub max_rep: Option<Arc<RwLock<RepNode>>>,
+    // pub representative: Option<Arc<RwLock<RepNode<K>>>>
+    pub min_rep: Option<Arc<RwLock<RepNode<K>>>>,
+    pub max_rep: Option<Arc<RwLock<RepNode<K>>>>,
 }
 
 #[derive(Debug, Default, Clone)]
-pub struct RepNode {
-    pub key: Key,
-    pub left: Option<Weak<RwLock<RepNode>>>,
-    pub right: Option<Weak<RwLock<RepNode>>>,
-    pub bst_group: Option<Arc<RwLock<BinarySearchTreeGroup>>>,
+pub struct RepNode<K: UnsignedKey = Key> {
+    pub key: K,
+    pub left: Option<Weak<RwLock<RepNode<K>>>>,
+    pub right: Option<Weak<RwLock<RepNode<K>>>>,
+    pub bst_group: Option<Arc<RwLock<BinarySearchTreeGroup<K>>>>,
 }
 
-impl XFastTrie {
+impl<K: UnsignedKey> XFastTrie<K> {
     pub fn new(no_levels: usize) -> Self {
         let mut levels = Vec::with_capacity(no_levels + 1);
         let root = XFastLevel::default();
 
         // insert the root level
         // use a random key for the root level
-        root.table.insert(ROOT_KEY, XFastValue::default());
+        root.table
+            .insert(K::from_u64(ROOT_KEY), XFastValue::default());
         levels.push(root);
         for _ in 1..=no_levels {
             let new_level = XFastLevel::default();
@@ -78,8 +82,31 @@ impl XFastTrie {
         count
     }
 
+    /// estimated bytes used by the trie and its levels
+    /// counts the hash table buckets of every level plus the child nodes they point to,
+    /// the representatives are counted by `rep_nodes_size_in_bytes`
+    pub fn levels_size_in_bytes(&self) -> usize {
+        let child_size = arc_allocation_size::<RwLock<XFastValue<K>>>();
+        let mut bytes = size_of::<Self>() + self.levels.capacity() * size_of::<XFastLevel<K>>();
+        for level in &self.levels {
+            // one control byte per hash table bucket
+            bytes += level.table.capacity() * (size_of::<(K, XFastValue<K>)>() + 1);
+            for entry in level.table.iter() {
+                let children =
+                    entry.left_child.is_some() as usize + entry.right_child.is_some() as usize;
+                bytes += children * child_size;
+            }
+        }
+        bytes
+    }
+
+    /// bytes used by the linked list of representatives, without their BST groups
+    pub fn rep_nodes_size_in_bytes(&self) -> usize {
+        self.len() * arc_allocation_size::<RwLock<RepNode<K>>>()
+    }
+
     // find length of longest prefix of key
-    fn find_longest_prefix_length(&self, key: Key) -> usize {
+    fn find_longest_prefix_length(&self, key: K) -> usize {
         // check if tree is empty
         if self.levels[1].table.is_empty() {
             return 0;
@@ -103,7 +130,7 @@ impl XFastTrie {
         low as usize
     }
 
-    pub fn predecessor(&self, key: Key) -> Option<Arc<RwLock<RepNode>>> {
+    pub fn predecessor(&self, key: K) -> Option<Arc<RwLock<RepNode<K>>>> {
         // empty trie
         if self.levels[1].table.is_empty() {
             return None;
@@ -111,12 +138,12 @@ impl XFastTrie {
 
         let longest_prefix_length = self.find_longest_prefix_length(key);
 
-        if longest_prefix_length == 0 && key >> (self.no_levels - 1) == 1 {
+        if longest_prefix_length == 0 && key >> (self.no_levels - 1) == K::ONE {
             // find the max representative of the root level
-            if let Some(root_value) = self.levels[1].table.get(&0) {
+            if let Some(root_value) = self.levels[1].table.get(&K::ZERO) {
                 return Some(root_value.max_rep.clone()?);
             }
-        } else if longest_prefix_length == 0 && key >> (self.no_levels - 1) == 0 {
+        } else if longest_prefix_length == 0 && key >> (self.no_levels - 1) == K::ZERO {
             return None;
         }
 
@@ -151,7 +178,7 @@ impl XFastTrie {
         None
     }
 
-    pub fn successor(&self, key: Key) -> Option<Arc<RwLock<RepNode>>> {
+    pub fn successor(&self, key: K) -> Option<Arc<RwLock<RepNode<K>>>> {
         // empty trie
         if self.levels[1].table.is_empty() {
             return None;
@@ -159,11 +186,11 @@ impl XFastTrie {
 
         let longest_prefix_length = self.find_longest_prefix_length(key);
 
-        if longest_prefix_length == 0 && key >> (self.no_levels - 1) == 1 {
+        if longest_prefix_length == 0 && key >> (self.no_levels - 1) == K::ONE {
             return None;
-        } else if longest_prefix_length == 0 && key >> (self.no_levels - 1) == 0 {
+        } else if longest_prefix_length == 0 && key >> (self.no_levels - 1) == K::ZERO {
             // find the min representative of the root level
-            if let Some(root_value) = self.levels[1].table.get(&1) {
+            if let Some(root_value) = self.levels[1].table.get(&K::ONE) {
                 return Some(root_value.min_rep.clone()?);
             }
         }
@@ -200,8 +227,8 @@ impl XFastTrie {
         None
     }
 
-    //  TODO: support variable length keys
-    pub fn lookup(&self, key: Key) -> Option<Arc<RwLock<RepNode>>> {
+    // variable length keys are handled by StringXFastTrie on top of 64 bit prefixes
+    pub fn lookup(&self, key: K) -> Option<Arc<RwLock<RepNode<K>>>> {
         let x_fast_value = self.levels[self.no_levels as usize].table.get(&key)?;
         if let Some(min_rep) = &x_fast_value.min_rep {
             if let Ok(min_rep_guard) = min_rep.read() {
@@ -212,12 +239,15 @@ impl XFastTrie {
     }
 
     // insert a key into the x-fast trie
-    pub fn insert(&mut self, key: Key) {
+    pub fn insert(&mut self, key: K) {
+        // inserting an existing key would relink its representative to itself
+        if self.lookup(key).is_some() {
+            return;
+        }
+
         // step 1: find the longest prefix length
         let longest_prefix_length = self.find_longest_prefix_length(key);
 
-        println!("longest_prefix_length: {}", longest_prefix_length);
-
         let predecessor = self.predecessor(key);
         let successor = self.successor(key);
 
@@ -249,8 +279,8 @@ impl XFastTrie {
                     .table
                     .get_mut(&parent_prefix)
                 {
-                    let bit = (key >> (self.no_levels - prefix_length)) & 1;
-                    if bit == 0 {
+                    let bit = (key >> (self.no_levels - prefix_length)) & K::ONE;
+                    if bit == K::ZERO {
                         parent_value.left_child =
                             Some(Arc::new(RwLock::new(new_x_fast_value.clone())));
                     } else {
@@ -260,9 +290,9 @@ impl XFastTrie {
                 }
             } else {
                 // update root level child pointers
-                if let Some(mut root_value) = self.levels[0].table.get_mut(&ROOT_KEY) {
+                if let Some(mut root_value) = self.levels[0].table.get_mut(&K::from_u64(ROOT_KEY)) {
                     let bit = key >> (self.no_levels - prefix_length);
-                    if bit == 0 {
+                    if bit == K::ZERO {
                         root_value.left_child =
                             Some(Arc::new(RwLock::new(new_x_fast_value.clone())));
                     } else {
@@ -361,11 +391,105 @@ impl XFastTrie {
         }
     }
 
+    // remove a key from the x-fast trie
+    pub fn remove(&mut self, key: K) -> bool {
+        let Some(representative) = self.lookup(key) else {
+            return false;
+        };
+
+        // step 1: unlink the representative from the linked list
+        let (left, right) = match representative.read() {
+            Ok(rep) => (
+                rep.left.as_ref().and_then(|w| w.upgrade()),
+                rep.right.as_ref().and_then(|w| w.upgrade()),
+            ),
+            Err(_) => return false,
+        };
+
+        if let Some(left_rep) = &left
+            && let Ok(mut left_guard) = left_rep.write()
+        {
+            left_guard.right = right.as_ref().map(Arc::downgrade);
+        }
+        if let Some(right_rep) = &right
+            && let Ok(mut right_guard) = right_rep.write()
+        {
+            right_guard.left = left.as_ref().map(Arc::downgrade);
+        }
+
+        // step 2: update head and tail representatives
+        if self
+            .head_rep
+            .as_ref()
+            .is_some_and(|head| Arc::ptr_eq(head, &representative))
+        {
+            self.head_rep = right.clone();
+        }
+        if self
+            .tail_rep
+            .as_ref()
+            .is_some_and(|tail| Arc::ptr_eq(tail, &representative))
+        {
+            self.tail_rep = left.clone();
+        }
+
+        // step 3: remove the leaf, then walk up dropping prefixes with no keys below them
+        // and refreshing min and max representatives from the remaining children
+        self.levels[self.no_levels].table.remove(&key);
+
+        for prefix_length in (1..self.no_levels).rev() {
+            let prefix = key >> (self.no_levels - prefix_length);
+            let child_level = &self.levels[prefix_length + 1].table;
+            let left_reps = child_level
+                .get(&(prefix << 1))
+                .map(|value| (value.min_rep.clone(), value.max_rep.clone()));
+            let right_reps = child_level
+                .get(&((prefix << 1) | K::ONE))
+                .map(|value| (value.min_rep.clone(), value.max_rep.clone()));
+
+            if left_reps.is_none() && right_reps.is_none() {
+                self.levels[prefix_length].table.remove(&prefix);
+                continue;
+            }
+
+            if let Some(mut x_fast_value) = self.levels[prefix_length].table.get_mut(&prefix) {
+                if left_reps.is_none() {
+                    x_fast_value.left_child = None;
+                }
+                if right_reps.is_none() {
+                    x_fast_value.right_child = None;
+                }
+                x_fast_value.min_rep = left_reps
+                    .as_ref()
+                    .or(right_reps.as_ref())
+                    .and_then(|(min_rep, _)| min_rep.clone());
+                x_fast_value.max_rep = right_reps
+                    .as_ref()
+                    .or(left_reps.as_ref())
+                    .and_then(|(_, max_rep)| max_rep.clone());
+            }
+        }
+
+        // step 4: update root level child pointers
+        let top_bit = key >> (self.no_levels - 1);
+        if !self.levels[1].table.contains_key(&top_bit)
+            && let Some(mut root_value) = self.levels[0].table.get_mut(&K::from_u64(ROOT_KEY))
+        {
+            if top_bit == K::ZERO {
+                root_value.left_child = None;
+            } else {
+                root_value.right_child = None;
+            }
+        }
+
+        true
+    }
+
     pub fn pretty_print(&self) {
         print!("{}", self);
     }
 
-    fn format_linked_list(start: &Arc<RwLock<RepNode>>, f: &mut fmt::Formatter) -> fmt::Result {
+    fn format_linked_list(start: &Arc<RwLock<RepNode<K>>>, f: &mut fmt::Formatter) -> fmt::Result {
         if let Ok(node) = start.read() {
             write!(f, "  {} ", node.key)?;
 
@@ -381,7 +505,7 @@ impl XFastTrie {
     }
 
     fn format_linked_list_helper(
-        node: &Arc<RwLock<RepNode>>,
+        node: &Arc<RwLock<RepNode<K>>>,
         f: &mut fmt::Formatter,
     ) -> fmt::Result {
         if let Ok(node_guard) = node.read() {
@@ -398,7 +522,7 @@ impl XFastTrie {
     }
 }
 
-impl fmt::Display for XFastTrie {
+impl<K: UnsignedKey> fmt::Display for XFastTrie<K> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "\n=== X-Fast Trie Structure ===")?;
 
@@ -460,7 +584,7 @@ mod tests {
 
     #[test]
     fn test_single_insert() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         trie.insert(42);
 
         // verify head and tail are set
@@ -476,7 +600,7 @@ mod tests {
 
     #[test]
     fn test_multiple_inserts() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         let keys = vec![10, 5, 15, 3, 12];
 
         for key in &keys {
@@ -499,7 +623,7 @@ mod tests {
 
     #[test]
     fn test_predecessor() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         let keys = vec![10, 20, 30, 40];
 
         for key in &keys {
@@ -529,7 +653,7 @@ mod tests {
 
     #[test]
     fn test_successor() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         let keys = vec![10, 20, 30, 40];
 
         for key in &keys {
@@ -552,7 +676,7 @@ mod tests {
 
     #[test]
     fn test_lookup() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         let keys = vec![10, 5, 15, 3, 12];
 
         for key in &keys {
@@ -571,7 +695,7 @@ mod tests {
 
     #[test]
     fn test_edge_cases() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
 
         // predecessor of empty trie
         assert!(trie.predecessor(10).is_none());
@@ -626,7 +750,7 @@ mod tests {
 
     #[test]
     fn test_min_max_values_comprehensive() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         let keys = vec![10, 5, 15, 3, 12];
 
         for key in &keys {
@@ -672,7 +796,7 @@ mod tests {
 
     #[test]
     fn test_min_max_single_key() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         trie.insert(42); // 42 = 0b00101010
 
         // all nodes should have min_rep=42 and max_rep=42
@@ -703,7 +827,7 @@ mod tests {
 
     #[test]
     fn test_min_max_adjacent_keys() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
         trie.insert(8); // 0b00001000
         trie.insert(9); // 0b00001001
 
@@ -723,7 +847,7 @@ mod tests {
 
     #[test]
     fn test_min_max_sequential_insertion() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
 
         // insert in increasing order
         for key in [1, 2, 3, 4, 5] {
@@ -744,7 +868,7 @@ mod tests {
 
     #[test]
     fn test_min_max_reverse_insertion() {
-        let mut trie = XFastTrie::new(8);
+        let mut trie: XFastTrie = XFastTrie::new(8);
 
         // insert in decreasing order
         for key in [5, 4, 3, 2, 1] {
@@ -761,7 +885,7 @@ mod tests {
 
     #[test]
     fn test_min_max_sparse_keys() {
-        let mut trie = XFastTrie::new(16);
+        let mut trie: XFastTrie = XFastTrie::new(16);
 
         // insert sparse keys with large gaps
         trie.insert(1); // 0b0000000000000001
@@ -782,4 +906,91 @@ mod tests {
         verify_min_max(&trie, 16, 0b0000000010000000, 128, 128);
         verify_min_max(&trie, 16, 0b0000000011111111, 255, 255);
     }
+
+    #[test]
+    fn test_remove() {
+        let mut trie: XFastTrie = XFastTrie::new(8);
+        let keys = vec![10, 5, 15, 3, 12];
+        for key in &keys {
+            trie.insert(*key);
+        }
+
+        assert!(!trie.remove(4));
+        assert!(trie.remove(10));
+        assert!(!trie.remove(10));
+
+        assert!(trie.lookup(10).is_none());
+        assert_eq!(trie.len(), 4);
+        verify_min_max(&trie, 5, 0b00001, 12, 15);
+        verify_min_max(&trie, 1, 0b0, 3, 15);
+        assert!(trie.levels[6].table.get(&0b000010).is_none());
+
+        // predecessor and successor skip the removed key
+        assert_eq!(trie.predecessor(11).unwrap().read().unwrap().key, 5);
+        assert_eq!(trie.successor(6).unwrap().read().unwrap().key, 12);
+
+        // removing the extremes moves head and tail
+        assert!(trie.remove(3));
+        assert!(trie.remove(15));
+        assert_eq!(trie.head_rep.as_ref().unwrap().read().unwrap().key, 5);
+        assert_eq!(trie.tail_rep.as_ref().unwrap().read().unwrap().key, 12);
+        verify_min_max(&trie, 1, 0b0, 5, 12);
+
+        assert!(trie.remove(5));
+        assert!(trie.remove(12));
+        assert!(trie.head_rep.is_none());
+        assert!(trie.tail_rep.is_none());
+        assert!(trie.levels[1].table.is_empty());
+        assert!(trie.predecessor(100).is_none());
+
+        // the trie is usable again after being emptied
+        trie.insert(200);
+        assert_eq!(trie.predecessor(255).unwrap().read().unwrap().key, 200);
+    }
+
+    #[test]
+    fn test_key_widths() {
+        // 32 bit keys use one level per bit, up to the largest key
+        let mut trie = XFastTrie::<u32>::new(32);
+        for key in [0, 7, 1 << 31, u32::MAX] {
+            trie.insert(key);
+        }
+        assert_eq!(trie.levels.len(), 33);
+        assert_eq!(
+            trie.predecessor(u32::MAX - 1).unwrap().read().unwrap().key,
+            1 << 31
+        );
+        assert_eq!(trie.successor(8).unwrap().read().unwrap().key, 1 << 31);
+        assert_eq!(trie.predecessor(6).unwrap().read().unwrap().key, 0);
+        assert!(trie.remove(u32::MAX));
+        assert!(trie.successor((1 << 31) + 1).is_none());
+
+        // 128 bit keys that differ only far below the top 64 bits
+        let high = 0xdead_beef_u128 << 96;
+        let mut trie = XFastTrie::<u128>::new(128);
+        for key in [high | 5, high | (1 << 70), high | (1 << 70) | 3, u128::MAX] {
+            trie.insert(key);
+        }
+        assert_eq!(
+            trie.predecessor(high | (1 << 70) | 2)
+                .unwrap()
+                .read()
+                .unwrap()
+                .key,
+            high | (1 << 70)
+        );
+        assert_eq!(
+            trie.successor(high | 6).unwrap().read().unwrap().key,
+            high | (1 << 70)
+        );
+        assert!(trie.predecessor(high | 4).is_none());
+        assert_eq!(
+            trie.successor(high | (1 << 71))
+                .unwrap()
+                .read()
+                .unwrap()
+                .key,
+            u128::MAX
+        );
+    }
 }
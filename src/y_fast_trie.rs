@@ -1,22 +1,26 @@
 use crate::Key;
+use crate::UnsignedKey;
 use crate::binary_search_tree::BinarySearchTreeGroup;
 use crate::infix_store::InfixStore;
+use crate::memory::MemoryUsage;
+use crate::utils::arc_allocation_size;
 use crate::x_fast_trie::XFastTrie;
 use std::fmt;
+use std::mem::size_of;
 use std::sync::{Arc, RwLock};
 
-pub struct YFastTrie {
-    pub x_fast_trie: XFastTrie,
+pub struct YFastTrie<K: UnsignedKey = Key> {
+    pub x_fast_trie: XFastTrie<K>,
 }
 
-impl YFastTrie {
+impl<K: UnsignedKey> YFastTrie<K> {
     pub fn new(no_levels: usize) -> Self {
         Self {
             x_fast_trie: XFastTrie::new(no_levels),
         }
     }
 
-    pub fn new_with_keys(keys: &[Key], no_levels: usize) -> Self {
+    pub fn new_with_keys(keys: &[K], no_levels: usize) -> Self {
         if keys.is_empty() {
             return Self::new(no_levels);
         }
@@ -76,13 +80,60 @@ impl YFastTrie {
         total
     }
 
+    /// all keys in sorted order
+    pub fn keys(&self) -> Vec<K> {
+        let mut keys = Vec::new();
+        let mut current = self.x_fast_trie.head_rep.clone();
+        while let Some(node) = current {
+            let Ok(n) = node.read() else {
+                break;
+            };
+            if let Some(bst_group) = &n.bst_group
+                && let Ok(bst) = bst_group.read()
+            {
+                keys.extend(Self::collect_bst_keys(&bst.root));
+            }
+            current = n.right.as_ref().and_then(|w| w.upgrade());
+        }
+        keys
+    }
+
+    /// estimated memory split between the trie parts, `key_count` is the number of keys
+    /// in the BST groups
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            x_fast_levels: size_of::<Self>() - size_of::<XFastTrie<K>>()
+                + self.x_fast_trie.levels_size_in_bytes(),
+            rep_nodes: self.x_fast_trie.rep_nodes_size_in_bytes(),
+            ..Default::default()
+        };
+
+        let mut current = self.x_fast_trie.head_rep.clone();
+        while let Some(node) = current {
+            let Ok(n) = node.read() else {
+                break;
+            };
+            if let Some(bst_group) = &n.bst_group
+                && let Ok(bst) = bst_group.read()
+            {
+                usage.bst_nodes += arc_allocation_size::<RwLock<BinarySearchTreeGroup<K>>>()
+                    - size_of::<BinarySearchTreeGroup<K>>()
+                    + bst.size_in_bytes();
+                usage.infix_stores += bst.infix_stores_size_in_bytes();
+                usage.key_count += bst.len();
+            }
+            current = n.right.as_ref().and_then(|w| w.upgrade());
+        }
+        usage
+    }
+
     pub fn sample_count(&self) -> usize {
         self.x_fast_trie.len()
     }
 
-    pub fn get_infix_store(&self, key: Key) -> Option<Arc<RwLock<InfixStore>>> {
-        // find the boundary representative
-        let rep_node = self.x_fast_trie.lookup(key)?;
+    pub fn get_infix_store(&self, key: K) -> Option<Arc<RwLock<InfixStore>>> {
+        // find the boundary representative of the bucket holding the key
+        let rep_node = self.x_fast_trie.predecessor(key)?;
         let rep = rep_node.read().ok()?;
 
         // get the BST group and call its get_infix_store
@@ -95,7 +146,7 @@ impl YFastTrie {
         None
     }
 
-    pub fn set_infix_store(&mut self, key: Key, infix_store: InfixStore) {
+    pub fn set_infix_store(&mut self, key: K, infix_store: InfixStore) {
         // find the boundary representative
         if let Some(rep_node) = self.x_fast_trie.predecessor(key) {
             if let Ok(rep) = rep_node.read() {
@@ -108,11 +159,100 @@ impl YFastTrie {
         }
     }
 
-    // TODO: add insert method
+    /// insert a key, splitting its bucket once it holds more than 2 log U keys
+    pub fn insert(&mut self, key: K) {
+        if self.contains(key) {
+            return;
+        }
+
+        let Some(rep_node) = self.x_fast_trie.predecessor(key) else {
+            // key precedes every boundary, so it starts a bucket of its own
+            self.x_fast_trie.insert(key);
+            if let Some(rep_node) = self.x_fast_trie.lookup(key)
+                && let Ok(rep) = rep_node.read()
+                && let Some(bst_group) = &rep.bst_group
+                && let Ok(mut bst) = bst_group.write()
+            {
+                bst.insert(key);
+            }
+            return;
+        };
+
+        let Some(bst_group) = rep_node.read().ok().and_then(|rep| rep.bst_group.clone()) else {
+            return;
+        };
+
+        // insert into the predecessor's bucket and split off its upper half if it grew too large
+        let upper_group = {
+            let Ok(mut bst) = bst_group.write() else {
+                return;
+            };
+            bst.insert(key);
+
+            if bst.len() > 2 * self.x_fast_trie.no_levels {
+                let keys = Self::collect_bst_keys(&bst.root);
+                let boundary_key = keys[keys.len() / 2];
+                Some((boundary_key, bst.split_off(boundary_key)))
+            } else {
+                None
+            }
+        };
+
+        // the upper half gets its own boundary representative
+        if let Some((boundary_key, upper_bst)) = upper_group {
+            self.x_fast_trie.insert(boundary_key);
+            if let Some(rep_node) = self.x_fast_trie.lookup(boundary_key)
+                && let Ok(mut rep) = rep_node.write()
+            {
+                rep.bst_group = Some(Arc::new(RwLock::new(upper_bst)));
+            }
+        }
+    }
+
     // TODO: add next, previous methods
     // TODO: create an iterator for the trie
 
-    pub fn predecessor(&self, key: Key) -> Option<Key> {
+    // remove a key, returns false if the key is not in the trie
+    pub fn remove(&mut self, key: K) -> bool {
+        let Some(rep_node) = self.x_fast_trie.predecessor(key) else {
+            return false;
+        };
+
+        let Some(bst_group) = rep_node.read().ok().and_then(|rep| rep.bst_group.clone()) else {
+            return false;
+        };
+
+        let (removed, new_boundary) = {
+            let Ok(mut bst) = bst_group.write() else {
+                return false;
+            };
+            let removed = bst.remove(key);
+            let keys = Self::collect_bst_keys(&bst.root);
+            (removed, keys.first().copied())
+        };
+
+        if !removed {
+            return false;
+        }
+
+        // the removed key was the bucket's boundary, so the next key in the bucket takes over
+        let is_boundary = rep_node.read().map(|rep| rep.key == key).unwrap_or(false);
+        if is_boundary {
+            self.x_fast_trie.remove(key);
+            if let Some(boundary_key) = new_boundary {
+                self.x_fast_trie.insert(boundary_key);
+                if let Some(rep_node) = self.x_fast_trie.lookup(boundary_key)
+                    && let Ok(mut rep) = rep_node.write()
+                {
+                    rep.bst_group = Some(bst_group);
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn predecessor(&self, key: K) -> Option<K> {
         // find the boundary representative
         let rep_node = self.x_fast_trie.predecessor(key)?;
         let rep = rep_node.read().ok()?;
@@ -127,7 +267,7 @@ impl YFastTrie {
         Some(rep.key)
     }
 
-    pub fn predecessor_infix_store(&self, key: Key) -> Option<Arc<RwLock<InfixStore>>> {
+    pub fn predecessor_infix_store(&self, key: K) -> Option<Arc<RwLock<InfixStore>>> {
         // find boundary via x-fast trie
         let rep_node = self.x_fast_trie.predecessor(key)?;
         let rep = rep_node.read().ok()?;
@@ -141,7 +281,7 @@ impl YFastTrie {
         None
     }
 
-    pub fn successor_infix_store(&self, key: Key) -> Option<Arc<RwLock<InfixStore>>> {
+    pub fn successor_infix_store(&self, key: K) -> Option<Arc<RwLock<InfixStore>>> {
         // find the containing bucket via predecessor boundary
         if let Some(rep_node) = self.x_fast_trie.predecessor(key) {
             if let Ok(rep) = rep_node.read() {
@@ -172,7 +312,7 @@ impl YFastTrie {
         None
     }
 
-    pub fn successor(&self, key: Key) -> Option<Key> {
+    pub fn successor(&self, key: K) -> Option<K> {
         // find the containing bucket via predecessor boundary
         if let Some(rep_node) = self.x_fast_trie.predecessor(key) {
             if let Ok(rep) = rep_node.read() {
@@ -206,7 +346,7 @@ impl YFastTrie {
         None
     }
 
-    pub fn contains(&self, key: Key) -> bool {
+    pub fn contains(&self, key: K) -> bool {
         // first check x-fast trie for direct hit
         if self.x_fast_trie.lookup(key).is_some() {
             return true;
@@ -231,15 +371,15 @@ impl YFastTrie {
     }
 
     // helper to collect all keys from BST in sorted order
-    fn collect_bst_keys(node: &Option<Box<crate::binary_search_tree::TreeNode>>) -> Vec<Key> {
+    fn collect_bst_keys(node: &Option<Box<crate::binary_search_tree::TreeNode<K>>>) -> Vec<K> {
         let mut keys = Vec::new();
         Self::collect_bst_keys_recursive(node, &mut keys);
         keys
     }
 
     fn collect_bst_keys_recursive(
-        node: &Option<Box<crate::binary_search_tree::TreeNode>>,
-        keys: &mut Vec<Key>,
+        node: &Option<Box<crate::binary_search_tree::TreeNode<K>>>,
+        keys: &mut Vec<K>,
     ) {
         if let Some(n) = node {
             Self::collect_bst_keys_recursive(&n.left, keys);
@@ -249,7 +389,7 @@ impl YFastTrie {
     }
 }
 
-impl fmt::Display for YFastTrie {
+impl<K: UnsignedKey> fmt::Display for YFastTrie<K> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(
             f,
@@ -363,14 +503,14 @@ mod tests {
 
     #[test]
     fn test_single_key() {
-        let trie = YFastTrie::new_with_keys(&[42], 8);
+        let trie: YFastTrie = YFastTrie::new_with_keys(&[42], 8);
         assert!(trie.contains(42));
     }
 
     #[test]
     fn test_basic_contains() {
         let keys = vec![10, 20, 30, 40, 50, 60, 70, 80];
-        let trie = YFastTrie::new_with_keys(&keys, 8);
+        let trie: YFastTrie = YFastTrie::new_with_keys(&keys, 8);
 
         for &key in &keys {
             assert!(trie.contains(key), "key {} should be in trie", key);
@@ -425,7 +565,7 @@ mod tests {
     #[test]
     fn test_predecessor() {
         let keys = vec![10, 20, 30, 40, 50];
-        let trie = YFastTrie::new_with_keys(&keys, 8);
+        let trie: YFastTrie = YFastTrie::new_with_keys(&keys, 8);
 
         // exact matches
         assert_eq!(trie.predecessor(10), Some(10));
@@ -448,7 +588,7 @@ mod tests {
     #[test]
     fn test_successor() {
         let keys = vec![10, 20, 30, 40, 50];
-        let trie = YFastTrie::new_with_keys(&keys, 8);
+        let trie: YFastTrie = YFastTrie::new_with_keys(&keys, 8);
 
         // exact matches
         assert_eq!(trie.successor(10), Some(10));
@@ -595,4 +735,126 @@ mod tests {
         assert!(trie.predecessor_infix_store(2).is_none());
         assert!(trie.successor_infix_store(1000).is_none());
     }
+
+    #[test]
+    fn test_insert() {
+        let mut trie = YFastTrie::new(8);
+
+        // mixed order so keys land before, inside and after existing buckets
+        let keys: Vec<Key> = (0..100).map(|i| (i * 37) % 100 * 2 + 3).collect();
+        for &key in &keys {
+            trie.insert(key);
+        }
+        // duplicates are ignored
+        trie.insert(keys[0]);
+
+        assert_eq!(trie.len(), keys.len());
+        for &key in &keys {
+            assert!(trie.contains(key), "key {} should be in trie", key);
+        }
+        assert!(!trie.contains(4));
+
+        // buckets were split to stay around 2 log U keys
+        assert!(trie.sample_count() > 1);
+
+        assert_eq!(trie.predecessor(10), Some(9));
+        assert_eq!(trie.successor(10), Some(11));
+        assert_eq!(trie.predecessor(2), None);
+        assert_eq!(trie.successor(202), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let keys: Vec<Key> = (0..100).map(|i| i * 2 + 3).collect();
+        let mut y_fast_trie = YFastTrie::new_with_keys(&keys, 8);
+        y_fast_trie.set_infix_store(53, InfixStore::default());
+
+        assert!(!y_fast_trie.remove(4));
+        assert!(!y_fast_trie.remove(250));
+
+        // remove every boundary key and a few interior keys
+        let boundaries: Vec<Key> = keys.iter().step_by(8).copied().collect();
+        for key in boundaries.iter().chain([7, 53].iter()) {
+            assert!(y_fast_trie.remove(*key), "failed to remove {}", key);
+            assert!(!y_fast_trie.contains(*key));
+        }
+
+        let remaining: Vec<Key> = keys
+            .iter()
+            .copied()
+            .filter(|key| !boundaries.contains(key) && *key != 7 && *key != 53)
+            .collect();
+        assert_eq!(y_fast_trie.len(), remaining.len());
+        for window in remaining.windows(2) {
+            assert!(y_fast_trie.contains(window[0]));
+            assert_eq!(y_fast_trie.successor(window[0] + 1), Some(window[1]));
+            assert_eq!(y_fast_trie.predecessor(window[1] - 1), Some(window[0]));
+        }
+        assert!(y_fast_trie.get_infix_store(53).is_none());
+
+        for key in &remaining {
+            assert!(y_fast_trie.remove(*key));
+        }
+        assert_eq!(y_fast_trie.len(), 0);
+        assert_eq!(y_fast_trie.predecessor(200), None);
+    }
+
+    #[test]
+    fn test_keys() {
+        let keys: Vec<Key> = (0..100).map(|i| i * 2 + 3).collect();
+        let mut y_fast_trie = YFastTrie::new_with_keys(&keys, 8);
+        assert_eq!(y_fast_trie.keys(), keys);
+
+        y_fast_trie.insert(0);
+        y_fast_trie.remove(53);
+        let mut expected: Vec<Key> = keys.iter().copied().filter(|&key| key != 53).collect();
+        expected.insert(0, 0);
+        assert_eq!(y_fast_trie.keys(), expected);
+
+        assert!(YFastTrie::<u64>::new(8).keys().is_empty());
+    }
+
+    #[test]
+    fn test_u16_keys() {
+        let keys: Vec<u16> = (0..3000).map(|i| i * 17 + 4).collect();
+        let mut y_fast_trie = YFastTrie::<u16>::new_with_keys(&keys, 16);
+        assert_eq!(y_fast_trie.len(), keys.len());
+        assert_eq!(y_fast_trie.sample_count(), keys.len().div_ceil(16));
+
+        assert!(y_fast_trie.contains(4 + 17 * 1000));
+        assert!(!y_fast_trie.contains(5));
+        assert_eq!(y_fast_trie.predecessor(30), Some(21));
+        assert_eq!(y_fast_trie.successor(30), Some(38));
+        assert_eq!(y_fast_trie.successor(u16::MAX), None);
+
+        y_fast_trie.insert(u16::MAX);
+        assert!(y_fast_trie.remove(21));
+        assert_eq!(y_fast_trie.predecessor(30), Some(4));
+        assert_eq!(y_fast_trie.successor(60_000), Some(u16::MAX));
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let keys: Vec<u64> = (0..1000).map(|i| i * 13).collect();
+        let mut trie = YFastTrie::new_with_keys(&keys, 64);
+        let usage = trie.memory_usage();
+        assert_eq!(usage.key_count, keys.len());
+        assert_eq!(usage.infix_stores, 0);
+        assert_eq!(
+            usage.rep_nodes,
+            trie.sample_count() * arc_allocation_size::<RwLock<crate::x_fast_trie::RepNode>>()
+        );
+
+        trie.set_infix_store(0, InfixStore::new_with_infixes(&[3, 5, 9], 8));
+        let with_store = trie.memory_usage();
+        let store_bytes = trie
+            .get_infix_store(0)
+            .unwrap()
+            .read()
+            .unwrap()
+            .size_in_bytes();
+        assert_eq!(with_store.infix_stores, store_bytes);
+        assert!(with_store.bst_nodes > usage.bst_nodes);
+        assert_eq!(with_store.x_fast_levels, usage.x_fast_levels);
+    }
 }